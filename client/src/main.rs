@@ -1,54 +1,559 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
 use std::str::FromStr;
-use byte_unit::Byte;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use rocky_rs::listener::listener::{
     listener_client::ListenerClient,
+    controller_client::ControllerClient,
+    DispatchRequest,
+    DispatchedJob,
+    ListAgentsRequest,
+    JobRequest,
+    JobState,
+    JobStatus,
+    ListJobsRequest,
+    CapabilitiesRequest,
     Operation,
     SendRequest,
     Mtu,
+    ImmMode,
+    ConnectionType,
+    Matrix,
+    QueryResultsRequest,
 };
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use tonic::transport::Channel;
+use output::output::Format;
+use pattern::pattern::{Fleet, Pattern};
+use scenario::scenario::{parse_size, Flow, Scenario};
+pub mod output;
+pub mod pattern;
+pub mod scenario;
 
 #[derive(Parser, Debug)]
-struct Args{
+struct Cli{
+    #[clap(short, long, requires = "initiator_port")]
+    initiator: Option<String>,
+    #[clap(long, requires = "initiator")]
+    initiator_port: Option<u16>,
+    #[clap(long, default_value = "table")]
+    output: Format,
+    // leave out the csv header when appending to a file
+    #[clap(long)]
+    no_header: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command{
+    /// Submit a job to the initiator
+    Run(Box<RunArgs>),
+    /// Print the status and results of a job
+    Status{
+        #[clap(short, long)]
+        id: u32,
+    },
+    /// Cancel a running job
+    Cancel{
+        #[clap(short, long)]
+        id: u32,
+    },
+    /// List the jobs of the initiator
+    List,
+    /// Print the status of a job whenever it changes until it is finished,
+    /// the exit code reflects the outcome of the job
+    Watch{
+        #[clap(short, long)]
+        id: u32,
+    },
+    /// Print the capabilities of the initiator
+    Caps,
+    /// Run scenarios of several flows
+    #[command(subcommand)]
+    Scenario(ScenarioCommand),
+    /// Run a collective traffic pattern (incast, all_to_all, ring or
+    /// permutation) between hosts that all run rocky-rs
+    Pattern(Box<PatternArgs>),
+    /// Query the finished jobs the initiator kept in its results database
+    Results(QueryArgs),
+    /// Use a controller that dispatches jobs to its agents
+    Controller{
+        // host:port of the controller
+        #[clap(long)]
+        address: String,
+        #[command(subcommand)]
+        command: ControllerCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ControllerCommand{
+    /// List the agents registered with the controller
+    Agents,
+    /// Run a job from the initiator of one agent to the server of another
+    Dispatch(Box<DispatchArgs>),
+    /// Print the latest status of a dispatched job
+    Status{
+        #[clap(short, long)]
+        id: u32,
+    },
+    /// List the dispatched jobs
+    List,
+    /// Query the jobs the controller kept in its results database
+    Results(QueryArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct QueryArgs{
+    // initiator host or host:port
+    #[clap(long)]
+    from: Option<String>,
+    // server host or host:port
+    #[clap(long)]
+    to: Option<String>,
+    // unix time in seconds of the oldest result
+    #[clap(long, conflicts_with = "last")]
+    since: Option<u64>,
+    // unix time in seconds of the newest result
+    #[clap(long)]
+    until: Option<u64>,
+    // results of the last seconds
+    #[clap(long)]
+    last: Option<u64>,
+    #[clap(long)]
+    op: Option<ClientOperation>,
+    // message size like "64KiB" the job measured
+    #[clap(long)]
+    size: Option<String>,
+    // only jobs with all these tags
+    #[clap(long = "tag")]
+    tags: Vec<String>,
+    // most recent results, the server caps it at 1000 if unset
+    #[clap(long)]
+    limit: Option<u32>,
+}
+
+impl QueryArgs {
+    fn request(self) -> Result<QueryResultsRequest, String> {
+        let since = match self.last {
+            Some(last) => SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs().saturating_sub(last),
+            None => self.since.unwrap_or(0),
+        };
+        let message_size = match self.size {
+            Some(size) => scenario::scenario::parse_size(&size)?,
+            None => 0,
+        };
+        Ok(QueryResultsRequest{
+            from_unix_ms: since.saturating_mul(1000),
+            to_unix_ms: self.until.map(|until| until.saturating_mul(1000)).unwrap_or(0),
+            initiator: self.from.unwrap_or_default(),
+            server: self.to.unwrap_or_default(),
+            op: self.op.map(|op| Operation::from(op).into()),
+            message_size,
+            tags: self.tags,
+            limit: self.limit.unwrap_or(0),
+        })
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct DispatchArgs{
+    // agent id of the initiator
+    #[clap(long)]
+    from: String,
+    // agent id of the server
+    #[clap(long)]
+    to: String,
+    // the controller picks an unused job id if unset, ids from 2^31 up are
+    // reserved for the ones it picks
+    #[clap(long)]
+    job_id: Option<u32>,
+    #[command(flatten)]
+    job: JobArgs,
+    // block until the job is finished and exit with its outcome
+    #[clap(long)]
+    wait: bool,
+    // seconds to wait for the job before giving up
+    #[clap(long, requires = "wait")]
+    wait_timeout: Option<u64>,
+}
+
+#[derive(clap::Args, Debug)]
+struct PatternArgs{
+    pattern: Pattern,
+    #[clap(long, value_delimiter = ',', required = true)]
+    hosts: Vec<String>,
+    #[clap(long)]
+    host_initiator_port: u16,
+    #[clap(long)]
+    host_server_port: u16,
+    // receiver of an incast, the first host if unset
+    #[clap(long)]
+    target: Option<String>,
+    // seed of a permutation, random if unset
+    #[clap(long)]
+    seed: Option<u64>,
+    // job id of the first flow, the other flows count up from it
+    #[clap(long, default_value = "1")]
+    first_id: u32,
+    #[clap(long, default_value = "send")]
+    op: String,
+    #[clap(long, value_delimiter = ',')]
+    sizes: Vec<String>,
+    #[clap(long, default_value = "1")]
+    messages: u32,
+    // messages per second on every queue pair
+    #[clap(long, default_value = "0")]
+    rate: u32,
+    #[clap(long, default_value = "1")]
+    qps: u32,
+    #[clap(long)]
+    mtu: Option<u32>,
+    #[clap(long)]
+    tx_depth: Option<u32>,
+    #[clap(long)]
+    connection_type: Option<String>,
+    // kept with the results of every flow next to pattern=<pattern>
+    #[clap(long = "tag")]
+    tags: Vec<String>,
+    // seconds between submitting the flows and their common start
+    #[clap(long, default_value = "2")]
+    start_delay: f64,
+    // seconds to wait for every flow after submitting it
+    #[clap(long)]
+    wait_timeout: Option<u64>,
+}
+
+impl PatternArgs {
+    fn scenario(self) -> Result<Scenario, String> {
+        let fleet = Fleet{
+            hosts: self.hosts,
+            initiator_port: self.host_initiator_port,
+            server_port: self.host_server_port,
+        };
+        let seed = self.seed.unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64);
+        let template = Flow{
+            id: self.first_id,
+            initiator: None,
+            server: String::new(),
+            op: self.op,
+            sizes: self.sizes,
+            messages: self.messages,
+            rate: self.rate,
+            start: 0.0,
+            qps: self.qps,
+            mtu: self.mtu,
+            tx_depth: self.tx_depth,
+            connection_type: self.connection_type,
+            tags: self.tags,
+        };
+        pattern::pattern::scenario(self.pattern, &fleet, self.target.as_deref(), seed, &template, self.start_delay)
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum ScenarioCommand{
+    /// Submit the flows of a yaml or toml scenario file to their initiators,
+    /// wait for them and aggregate the results. Flows without an initiator
+    /// go to --initiator
+    Run{
+        file: PathBuf,
+        // seconds to wait for every flow after submitting it
+        #[clap(long)]
+        wait_timeout: Option<u64>,
+    },
+}
+
+#[derive(clap::Args, Debug)]
+struct RunArgs{
     #[clap(short, long)]
     id: u32,
     #[clap(short, long)]
     server: String,
-    #[clap(short, long)]
-    initiator: String,
-    #[clap(short, long)]
+    #[clap(long)]
     server_port: u16,
-    #[clap(short, long)]
-    initiator_port: u16,
+    #[command(flatten)]
+    job: JobArgs,
+    // block until the job is finished and exit with its outcome
+    #[clap(long)]
+    wait: bool,
+    // seconds to wait for the job before giving up
+    #[clap(long, requires = "wait")]
+    wait_timeout: Option<u64>,
+}
+
+impl Into<SendRequest> for RunArgs {
+    fn into(self) -> SendRequest {
+        SendRequest{
+            id: self.id,
+            address: format!("{}:{}", self.server, self.server_port),
+            ..self.job.into()
+        }
+    }
+}
+
+// JobArgs are the parameters of a job whether it runs on an initiator
+// directly or is dispatched by a controller
+#[derive(clap::Args, Debug)]
+struct JobArgs{
     #[clap(value_enum)]
     op: ClientOperation,
-    #[clap(short, long)]
-    message_size: Option<String>,
-    #[clap(short, long)]
+    #[clap(short, long, value_parser = parse_size)]
+    message_size: Option<u32>,
+    #[clap(long)]
     messages: Option<u32>,
-    #[clap(short, long)]
+    #[clap(long)]
     mtu: Option<MtuSize>,
+    #[clap(long)]
+    imm_mode: Option<ClientImmMode>,
+    #[clap(long)]
+    imm_data: Option<u32>,
+    #[clap(long)]
+    device: Option<String>,
+    #[clap(long)]
+    ib_port: Option<u32>,
+    #[clap(long)]
+    gid_index: Option<u32>,
+    #[clap(long)]
+    server_device: Option<String>,
+    #[clap(long)]
+    server_ib_port: Option<u32>,
+    #[clap(long)]
+    server_gid_index: Option<u32>,
+    #[clap(long)]
+    max_send_wr: Option<u32>,
+    #[clap(long)]
+    max_recv_wr: Option<u32>,
+    #[clap(long)]
+    max_sge: Option<u32>,
+    #[clap(long)]
+    cq_size: Option<u32>,
+    #[clap(long)]
+    max_inline_data: Option<u32>,
+    #[clap(long)]
+    retry_count: Option<u32>,
+    #[clap(long)]
+    rnr_retry: Option<u32>,
+    #[clap(long)]
+    timeout: Option<u32>,
+    #[clap(long)]
+    min_rnr_timer: Option<u32>,
+    // the qos options are rejected by the initiator until async-rdma can set
+    // the address vector of a queue pair
+    #[clap(long)]
+    traffic_class: Option<u32>,
+    #[clap(long)]
+    dscp: Option<u32>,
+    #[clap(long)]
+    service_level: Option<u32>,
+    #[clap(long)]
+    flow_label: Option<u32>,
+    #[clap(long)]
+    connection_type: Option<ClientConnectionType>,
+    #[clap(long)]
+    sge_count: Option<u32>,
+    #[clap(long, value_delimiter = ',', value_parser = parse_size)]
+    sge_sizes: Vec<u32>,
+    #[clap(long)]
+    sge_alignment: Option<u32>,
+    #[clap(long, value_delimiter = ',', value_parser = parse_size)]
+    sweep_sizes: Vec<u32>,
+    #[clap(long, value_parser = parse_size)]
+    sweep_min: Option<u32>,
+    #[clap(long, value_parser = parse_size)]
+    sweep_max: Option<u32>,
+    #[clap(long)]
+    tx_depth: Option<u32>,
+    #[clap(long, value_delimiter = ',')]
+    matrix_mtus: Vec<MtuSize>,
+    #[clap(long, value_delimiter = ',', value_parser = parse_size)]
+    matrix_sizes: Vec<u32>,
+    #[clap(long, value_delimiter = ',')]
+    matrix_tx_depths: Vec<u32>,
+    #[clap(long, value_delimiter = ',')]
+    matrix_connections: Vec<u32>,
+    #[clap(long)]
+    warmup_messages: Option<u32>,
+    #[clap(long)]
+    repetitions: Option<u32>,
+    // report interval in seconds
+    #[clap(long)]
+    interval: Option<f64>,
+    // messages per second on every connection
+    #[clap(long)]
+    message_rate: Option<u32>,
+    // labels like "rack=a3" kept with the results, may be repeated
+    #[clap(long = "tag")]
+    tags: Vec<String>,
+    // seconds the server waits for the next message before it reports the
+    // ones it received
+    #[clap(long)]
+    receive_timeout: Option<f64>,
+    // don't check the immediate data on the server
+    #[clap(long)]
+    skip_integrity_check: bool,
+}
+
+// exit codes for scripts, errors talking to the initiator exit with 1
+const EXIT_FAILED: u8 = 2;
+const EXIT_PARTIAL: u8 = 3;
+const EXIT_INTEGRITY: u8 = 4;
+const EXIT_TIMEOUT: u8 = 5;
+// the server did not report what it received
+const EXIT_UNVERIFIED: u8 = 6;
+
+// classify maps the outcome of a finished job to an exit code and the
+// reason it isn't 0, integrity errors take precedence over missing messages
+fn classify(job: &JobStatus) -> (u8, Option<String>) {
+    if job.state != JobState::JobCompleted as i32 {
+        return (EXIT_FAILED, Some(format!("job {} did not complete: {}", job.id, job.error)));
+    }
+    let delivery = match &job.delivery {
+        Some(delivery) => delivery,
+        None => return (EXIT_UNVERIFIED, Some(format!("job {}: the server did not report what it received", job.id))),
+    };
+    if delivery.integrity_errors > 0 {
+        return (EXIT_INTEGRITY, Some(format!("job {}: {} integrity errors", job.id, delivery.integrity_errors)));
+    }
+    if delivery.received < delivery.sent || delivery.receive_errors > 0 {
+        return (EXIT_PARTIAL, Some(format!("job {}: received {} of {} messages with {} receive errors", job.id, delivery.received, delivery.sent, delivery.receive_errors)));
+    }
+    (0, None)
+}
+
+// exit_code classifies a finished job and prints why it failed
+fn exit_code(job: &JobStatus) -> u8 {
+    let (code, reason) = classify(job);
+    if let Some(reason) = reason {
+        eprintln!("{}", reason);
+    }
+    code
 }
 
-impl Into<SendRequest> for Args {
+// run_scenario runs the flows of a scenario, prints their results and
+// returns the worst exit code of the flows
+async fn run_scenario(scenario: &Scenario, initiator: Option<&str>, wait_timeout: Option<u64>, format: &Format, header: bool) -> ExitCode {
+    let results = scenario::scenario::run(scenario, initiator, wait_timeout).await;
+    let summary = scenario::scenario::summarize(&results);
+    println!("{}", output::output::render_scenario(format, &scenario.name, &results, &summary, header));
+    ExitCode::from(scenario::scenario::exit_code(&results))
+}
+
+const DISPATCH_POLL_PERIOD: Duration = Duration::from_millis(500);
+
+// wait_for_dispatched polls the controller until a dispatched job is
+// finished or the controller lost track of it, None if the timeout expires
+async fn wait_for_dispatched(client: &mut ControllerClient<Channel>, id: u32, timeout: Option<u64>) -> Result<Option<DispatchedJob>, tonic::Status> {
+    let deadline = timeout.map(|secs| tokio::time::Instant::now() + Duration::from_secs(secs));
+    loop {
+        let job = client.get_job(JobRequest{ id }).await?.into_inner();
+        let running = job.status.as_ref().is_none_or(|status| status.state == JobState::JobRunning as i32);
+        if !running || !job.error.is_empty() {
+            return Ok(Some(job));
+        }
+        if deadline.is_some_and(|deadline| tokio::time::Instant::now() >= deadline) {
+            return Ok(None);
+        }
+        tokio::time::sleep(DISPATCH_POLL_PERIOD).await;
+    }
+}
+
+fn dispatched_exit_code(job: &DispatchedJob) -> u8 {
+    if !job.error.is_empty() {
+        eprintln!("controller lost track of job {}: {}", job.id, job.error);
+        return EXIT_FAILED;
+    }
+    job.status.as_ref().map_or(EXIT_FAILED, exit_code)
+}
+
+async fn connect(initiator: Option<&str>) -> Result<ListenerClient<Channel>, Box<dyn std::error::Error>> {
+    let initiator = initiator.ok_or("--initiator and --initiator-port are required")?;
+    Ok(ListenerClient::connect(format!("http://{}", initiator)).await?)
+}
+
+// wait_for follows a job until it is finished and returns its final status,
+// or None if the timeout expires first
+async fn wait_for(client: &mut ListenerClient<Channel>, id: u32, timeout: Option<u64>) -> Result<Option<JobStatus>, tonic::Status> {
+    let watch = async {
+        let mut updates = client.watch_job(JobRequest{ id }).await?.into_inner();
+        let mut last = None;
+        while let Some(job) = updates.message().await? {
+            last = Some(job);
+        }
+        last.ok_or_else(|| tonic::Status::unavailable(format!("watch of job {} ended without a status", id)))
+    };
+    match timeout {
+        Some(secs) => match tokio::time::timeout(Duration::from_secs(secs), watch).await {
+            Ok(job) => job.map(Some),
+            Err(_) => Ok(None),
+        },
+        None => watch.await.map(Some),
+    }
+}
+
+impl Into<SendRequest> for JobArgs {
     fn into(self) -> SendRequest {
         let mtu = if let Some(mtu) = self.mtu{
             Mtu::from(mtu).into()
         } else {
             Mtu::Mtu1024
         };
-        let message_size = if let Some(message_size) = self.message_size{
-            Byte::parse_str(&message_size, true).unwrap().as_u64() as u32
+        let matrix = if self.matrix_mtus.is_empty() && self.matrix_sizes.is_empty() && self.matrix_tx_depths.is_empty() && self.matrix_connections.is_empty() {
+            None
         } else {
-            8
+            Some(Matrix{
+                mtus: self.matrix_mtus.into_iter().map(|mtu| Mtu::from(mtu).into()).collect(),
+                message_sizes: self.matrix_sizes,
+                tx_depths: self.matrix_tx_depths,
+                connections: self.matrix_connections,
+            })
         };
         SendRequest{
-            id: self.id,
-            address: format!("{}:{}", self.server, self.server_port),
+            id: 0,
+            address: String::new(),
             op: Operation::from(self.op).into(),
-            message_size,
+            message_size: self.message_size.unwrap_or(8),
             messages: self.messages.unwrap_or(1),
             mtu: mtu.into(),
+            imm_mode: ImmMode::from(self.imm_mode.unwrap_or(ClientImmMode::Constant)).into(),
+            imm_data: self.imm_data.unwrap_or(1),
+            device: self.device,
+            ib_port: self.ib_port,
+            gid_index: self.gid_index,
+            server_device: self.server_device,
+            server_ib_port: self.server_ib_port,
+            server_gid_index: self.server_gid_index,
+            max_send_wr: self.max_send_wr,
+            max_recv_wr: self.max_recv_wr,
+            max_sge: self.max_sge,
+            cq_size: self.cq_size,
+            max_inline_data: self.max_inline_data,
+            retry_count: self.retry_count,
+            rnr_retry: self.rnr_retry,
+            timeout: self.timeout,
+            min_rnr_timer: self.min_rnr_timer,
+            traffic_class: self.traffic_class,
+            dscp: self.dscp,
+            service_level: self.service_level,
+            flow_label: self.flow_label,
+            connection_type: ConnectionType::from(self.connection_type.unwrap_or(ClientConnectionType::RcTcp)).into(),
+            sge_count: self.sge_count.unwrap_or(0),
+            sge_sizes: self.sge_sizes,
+            sge_alignment: self.sge_alignment.unwrap_or(0),
+            sweep_sizes: self.sweep_sizes,
+            sweep_min: self.sweep_min.unwrap_or(0),
+            sweep_max: self.sweep_max.unwrap_or(0),
+            tx_depth: self.tx_depth.unwrap_or(1),
+            matrix,
+            warmup_messages: self.warmup_messages.unwrap_or(0),
+            repetitions: self.repetitions.unwrap_or(1),
+            interval_ms: self.interval.map(|secs| (secs * 1000.0) as u32).unwrap_or(0),
+            message_rate: self.message_rate.unwrap_or(0),
+            start_at_unix_ns: 0,
+            tags: self.tags,
+            receive_timeout_ms: self.receive_timeout.map(|secs| (secs * 1000.0) as u32).unwrap_or(0),
+            skip_integrity_check: self.skip_integrity_check,
         }
     }
 }
@@ -57,6 +562,7 @@ impl Into<SendRequest> for Args {
 enum ClientOperation{
     Send,
     SendWithImm,
+    SendMixed,
 }
 
 impl FromStr for ClientOperation {
@@ -65,6 +571,7 @@ impl FromStr for ClientOperation {
         match s {
             "send" => Ok(ClientOperation::Send),
             "send_with_imm" => Ok(ClientOperation::SendWithImm),
+            "send_mixed" => Ok(ClientOperation::SendMixed),
             _ => Err("invalid operation".to_string()),
         }
     }
@@ -75,6 +582,68 @@ impl From<ClientOperation> for Operation {
         match op {
             ClientOperation::Send => Operation::Send,
             ClientOperation::SendWithImm => Operation::SendWithImm,
+            ClientOperation::SendMixed => Operation::SendMixed,
+        }
+    }
+}
+
+#[derive(Parser, Debug, Clone)]
+enum ClientImmMode{
+    Constant,
+    Sequence,
+    JobId,
+}
+
+impl FromStr for ClientImmMode {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "constant" => Ok(ClientImmMode::Constant),
+            "sequence" => Ok(ClientImmMode::Sequence),
+            "job_id" => Ok(ClientImmMode::JobId),
+            _ => Err("invalid imm mode".to_string()),
+        }
+    }
+}
+
+impl From<ClientImmMode> for ImmMode {
+    fn from(imm_mode: ClientImmMode) -> Self {
+        match imm_mode {
+            ClientImmMode::Constant => ImmMode::ImmConstant,
+            ClientImmMode::Sequence => ImmMode::ImmSequence,
+            ClientImmMode::JobId => ImmMode::ImmJobId,
+        }
+    }
+}
+
+#[derive(Parser, Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+enum ClientConnectionType{
+    RcTcp,
+    RcCm,
+    RcGrpc,
+}
+
+impl FromStr for ClientConnectionType {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rc_tcp" => Ok(ClientConnectionType::RcTcp),
+            "rc_cm" => Ok(ClientConnectionType::RcCm),
+            "rc_grpc" => Ok(ClientConnectionType::RcGrpc),
+            // async-rdma only creates reliable connected queue pairs
+            "ud" => Err("ud is not supported, async-rdma only creates reliable connected queue pairs".to_string()),
+            _ => Err("invalid connection type".to_string()),
+        }
+    }
+}
+
+impl From<ClientConnectionType> for ConnectionType {
+    fn from(connection_type: ClientConnectionType) -> Self {
+        match connection_type {
+            ClientConnectionType::RcTcp => ConnectionType::RcTcp,
+            ClientConnectionType::RcCm => ConnectionType::RcCm,
+            ClientConnectionType::RcGrpc => ConnectionType::RcGrpc,
         }
     }
 }
@@ -112,12 +681,136 @@ impl From<MtuSize> for Mtu {
 }
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
-    let initiator_address = format!("http://{}:{}",args.initiator, args.initiator_port);
-    let mut client = ListenerClient::connect(initiator_address).await?;
-    let request = tonic::Request::new(args.into());
-    let response = client.send(request).await?;
-    println!("RESPONSE={:?}", response);
-    Ok(())
+async fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    let initiator = cli.initiator.zip(cli.initiator_port).map(|(host, port)| format!("{}:{}", host, port));
+    let initiator = initiator.as_deref();
+    let format = cli.output;
+    let header = !cli.no_header;
+    match cli.command {
+        Command::Run(args) => {
+            let mut client = connect(initiator).await?;
+            let id = args.id;
+            let wait = args.wait;
+            let timeout = args.wait_timeout;
+            let request = tonic::Request::new((*args).into());
+            let reply = client.send(request).await?.into_inner();
+            if !wait {
+                println!("{}", output::output::render_reply(&format, id, &reply, header));
+                return Ok(ExitCode::SUCCESS);
+            }
+            match wait_for(&mut client, id, timeout).await? {
+                Some(job) => {
+                    println!("{}", output::output::render(&format, Some(&reply), &job, header));
+                    return Ok(ExitCode::from(exit_code(&job)));
+                },
+                None => {
+                    let job = client.get_job(JobRequest{ id }).await?.into_inner();
+                    println!("{}", output::output::render(&format, Some(&reply), &job, header));
+                    eprintln!("job {} did not finish within {} seconds", id, timeout.unwrap_or_default());
+                    return Ok(ExitCode::from(EXIT_TIMEOUT));
+                },
+            }
+        },
+        Command::Status{ id } => {
+            let mut client = connect(initiator).await?;
+            let job = client.get_job(JobRequest{ id }).await?.into_inner();
+            println!("{}", output::output::render(&format, None, &job, header));
+        },
+        Command::Cancel{ id } => {
+            let mut client = connect(initiator).await?;
+            let job = client.cancel_job(JobRequest{ id }).await?.into_inner();
+            println!("{}", output::output::render(&format, None, &job, header));
+        },
+        Command::List => {
+            let mut client = connect(initiator).await?;
+            let jobs = client.list_jobs(ListJobsRequest{}).await?.into_inner();
+            println!("{}", output::output::render_jobs(&format, &jobs.jobs, header));
+        },
+        Command::Watch{ id } => {
+            let mut client = connect(initiator).await?;
+            let mut updates = client.watch_job(JobRequest{ id }).await?.into_inner();
+            let mut header = header;
+            let mut last = None;
+            while let Some(job) = updates.message().await? {
+                println!("{}", output::output::render(&format, None, &job, header));
+                header = false;
+                last = Some(job);
+            }
+            if let Some(job) = last {
+                return Ok(ExitCode::from(exit_code(&job)));
+            }
+        },
+        Command::Caps => {
+            let mut client = connect(initiator).await?;
+            let caps = client.get_capabilities(CapabilitiesRequest{}).await?.into_inner();
+            println!("{}", output::output::render_capabilities(&format, &caps, header));
+        },
+        Command::Scenario(ScenarioCommand::Run{ file, wait_timeout }) => {
+            let scenario = scenario::scenario::load(&file)?;
+            return Ok(run_scenario(&scenario, initiator, wait_timeout, &format, header).await);
+        },
+        Command::Results(args) => {
+            let mut client = connect(initiator).await?;
+            let results = client.query_results(args.request()?).await?.into_inner();
+            println!("{}", output::output::render_results(&format, &results.results, header));
+        },
+        Command::Controller{ address, command } => {
+            let mut client = ControllerClient::connect(format!("http://{}", address)).await?;
+            match command {
+                ControllerCommand::Agents => {
+                    let agents = client.list_agents(ListAgentsRequest{}).await?.into_inner();
+                    println!("{}", output::output::render_agents(&format, &agents.agents, header));
+                },
+                ControllerCommand::Dispatch(args) => {
+                    let args = *args;
+                    let request = DispatchRequest{
+                        initiator: args.from,
+                        server: args.to,
+                        request: Some(SendRequest{
+                            id: args.job_id.unwrap_or(0),
+                            ..args.job.into()
+                        }),
+                    };
+                    let reply = client.dispatch(request).await?.into_inner();
+                    let id = reply.id;
+                    if !args.wait {
+                        let job = client.get_job(JobRequest{ id }).await?.into_inner();
+                        println!("{}", output::output::render_dispatched(&format, reply.reply.as_ref(), &job, header));
+                        return Ok(ExitCode::SUCCESS);
+                    }
+                    match wait_for_dispatched(&mut client, id, args.wait_timeout).await? {
+                        Some(job) => {
+                            println!("{}", output::output::render_dispatched(&format, reply.reply.as_ref(), &job, header));
+                            return Ok(ExitCode::from(dispatched_exit_code(&job)));
+                        },
+                        None => {
+                            let job = client.get_job(JobRequest{ id }).await?.into_inner();
+                            println!("{}", output::output::render_dispatched(&format, reply.reply.as_ref(), &job, header));
+                            eprintln!("job {} did not finish within {} seconds", id, args.wait_timeout.unwrap_or_default());
+                            return Ok(ExitCode::from(EXIT_TIMEOUT));
+                        },
+                    }
+                },
+                ControllerCommand::Status{ id } => {
+                    let job = client.get_job(JobRequest{ id }).await?.into_inner();
+                    println!("{}", output::output::render_dispatched(&format, None, &job, header));
+                },
+                ControllerCommand::List => {
+                    let jobs = client.list_jobs(ListJobsRequest{}).await?.into_inner();
+                    println!("{}", output::output::render_dispatched_jobs(&format, &jobs.jobs, header));
+                },
+                ControllerCommand::Results(args) => {
+                    let results = client.query_results(args.request()?).await?.into_inner();
+                    println!("{}", output::output::render_results(&format, &results.results, header));
+                },
+            }
+        },
+        Command::Pattern(args) => {
+            let wait_timeout = args.wait_timeout;
+            let scenario = args.scenario()?;
+            return Ok(run_scenario(&scenario, None, wait_timeout, &format, header).await);
+        },
+    }
+    Ok(ExitCode::SUCCESS)
 }
@@ -0,0 +1,650 @@
+use std::fmt::Write;
+use std::str::FromStr;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use rocky_rs::listener::listener::{
+    Agent, AgentState, CapabilitiesReply, Cell, Delivery, DispatchedJob, Interval, JobState, JobStatus, JobSummary, Latency, Measurement, Mtu, Operation, SendReply,
+    SendRequest, StoredResult,
+};
+use serde_json::{json, Value};
+use crate::scenario::scenario::{self, FlowResult, Outcome, Summary};
+
+#[derive(Debug, Clone)]
+pub enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Format::Table),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err("invalid output format".to_string()),
+        }
+    }
+}
+
+// render formats the reply to a send and the status of its job,
+// header controls the header row of csv output
+pub fn render(format: &Format, reply: Option<&SendReply>, job: &JobStatus, header: bool) -> String {
+    match format {
+        Format::Table => table(reply, job),
+        Format::Json => json(reply, job).to_string(),
+        Format::Csv => csv(job, header),
+    }
+}
+
+// render_reply formats the reply to a send of a job which was not waited
+// for, the job is still running so it has no results yet
+pub fn render_reply(format: &Format, id: u32, reply: &SendReply, header: bool) -> String {
+    let optional = |value: Option<u32>| value.map(|v| v.to_string()).unwrap_or_default();
+    match format {
+        Format::Table => format!("reply: {}\njob {} submitted", reply.message, id),
+        Format::Json => json!({
+            "id": id,
+            "reply": reply_json(reply),
+        }).to_string(),
+        Format::Csv => {
+            let mut out = String::new();
+            if header {
+                out.push_str("id,message,traffic_class,service_level,flow_label\n");
+            }
+            let _ = write!(out, "{},{},{},{},{}", id, csv_escape(&reply.message), optional(reply.traffic_class), optional(reply.service_level), optional(reply.flow_label));
+            out
+        },
+    }
+}
+
+// render_jobs formats a list of jobs
+pub fn render_jobs(format: &Format, jobs: &[JobSummary], header: bool) -> String {
+    match format {
+        Format::Table => {
+            let mut out = format!("{:>10} {:>14} error", "id", "state");
+            for job in jobs {
+                let _ = write!(out, "\n{:>10} {:>14} {}", job.id, job_state(job.state), job.error);
+            }
+            out
+        },
+        Format::Json => Value::from(jobs.iter().map(|job| json!({
+            "id": job.id,
+            "state": job_state(job.state),
+            "error": job.error,
+        })).collect::<Vec<_>>()).to_string(),
+        Format::Csv => {
+            let mut rows = Vec::new();
+            if header {
+                rows.push("id,state,error".to_string());
+            }
+            rows.extend(jobs.iter().map(|job| format!("{},{},{}", job.id, job_state(job.state), csv_escape(&job.error))));
+            rows.join("\n")
+        },
+    }
+}
+
+// render_capabilities formats the capabilities of an initiator
+pub fn render_capabilities(format: &Format, caps: &CapabilitiesReply, header: bool) -> String {
+    let operations = caps.operations.iter().map(|op| Operation::try_from(*op).map(|op| op.as_str_name()).unwrap_or("UNKNOWN")).collect::<Vec<_>>();
+    let mtus = caps.mtus.iter().map(|m| mtu(*m)).collect::<Vec<_>>();
+    match format {
+        Format::Table => {
+            let mut out = String::new();
+            let _ = writeln!(out, "version: {}", caps.version);
+            let _ = writeln!(out, "operations: {}", operations.join(", "));
+            let _ = writeln!(out, "transports: {}", caps.transports.join(", "));
+            let _ = writeln!(out, "mtus: {}", mtus.join(", "));
+            let _ = writeln!(out, "max message size: {}", caps.max_message_size);
+            for d in &caps.devices {
+                let _ = writeln!(out, "device {} port {}: {} {} {}", d.name, d.port, d.state, d.link_layer, d.rate);
+            }
+            out.trim_end().to_string()
+        },
+        Format::Json => json!({
+            "version": caps.version,
+            "operations": operations,
+            "transports": caps.transports,
+            "mtus": mtus,
+            "maxMessageSize": caps.max_message_size,
+            "devices": caps.devices.iter().map(|d| json!({
+                "name": d.name,
+                "port": d.port,
+                "state": d.state,
+                "linkLayer": d.link_layer,
+                "rate": d.rate,
+            })).collect::<Vec<_>>(),
+        }).to_string(),
+        // one row per device, the initiator wide fields repeat
+        Format::Csv => {
+            let mut rows = Vec::new();
+            if header {
+                rows.push("version,operations,transports,mtus,max_message_size,device,port,state,link_layer,rate".to_string());
+            }
+            let common = format!("{},{},{},{},{}", csv_escape(&caps.version), csv_escape(&operations.join(" ")), csv_escape(&caps.transports.join(" ")), csv_escape(&mtus.join(" ")), caps.max_message_size);
+            if caps.devices.is_empty() {
+                rows.push(format!("{},,,,,", common));
+            }
+            for d in &caps.devices {
+                rows.push(format!("{},{},{},{},{},{}", common, csv_escape(&d.name), d.port, csv_escape(&d.state), csv_escape(&d.link_layer), csv_escape(&d.rate)));
+            }
+            rows.join("\n")
+        },
+    }
+}
+
+// render_scenario formats one line per flow of a scenario and the aggregate
+// of all flows
+pub fn render_scenario(format: &Format, name: &str, results: &[FlowResult], summary: &Summary, header: bool) -> String {
+    let flow_state = |result: &FlowResult| match &result.outcome {
+        Outcome::Finished(job) => state(job),
+        Outcome::TimedOut(_) => "TIMEOUT",
+        Outcome::Failed(_) => "ERROR",
+    };
+    let flow_error = |result: &FlowResult| match &result.outcome {
+        Outcome::Finished(job) => job.error.clone(),
+        Outcome::TimedOut(_) => "wait timed out".to_string(),
+        Outcome::Failed(e) => e.clone(),
+    };
+    let flow_latency = |result: &FlowResult| result.job().and_then(|job| job.latency.clone()).unwrap_or_default();
+    match format {
+        Format::Table => {
+            let mut out = String::new();
+            if !name.is_empty() {
+                let _ = writeln!(out, "scenario {}", name);
+            }
+            let _ = writeln!(out, "{:>8} {:>22} {:>22} {:>14} {:>10} {:>12} {:>10} {:>10} error", "flow", "initiator", "server", "state", "messages", "Gb/s", "p50 us", "p99 us");
+            for result in results {
+                let totals = result.job().map(scenario::totals).unwrap_or_default();
+                let l = flow_latency(result);
+                let _ = writeln!(out, "{:>8} {:>22} {:>22} {:>14} {:>10} {:>12.3} {:>10.3} {:>10.3} {}", result.flow.id, result.initiator, result.flow.server, flow_state(result), totals.messages, totals.bandwidth_gbps, l.p50_us, l.p99_us, flow_error(result));
+            }
+            let _ = writeln!(out, "{} of {} flows succeeded, {} messages, {} bytes, {:.3} Gb/s aggregate", summary.completed, summary.flows, summary.totals.messages, summary.totals.bytes, summary.totals.bandwidth_gbps);
+            if let Some(l) = &summary.latency {
+                let _ = writeln!(out, "latency: {} messages, p50 {:.3} us, p99 {:.3} us, p99.9 {:.3} us, max {:.3} us", l.count, l.p50_us, l.p99_us, l.p999_us, l.max_us);
+            }
+            out.trim_end().to_string()
+        },
+        Format::Json => json!({
+            "name": name,
+            "flows": results.iter().map(|result| json!({
+                "id": result.flow.id,
+                "initiator": result.initiator,
+                "server": result.flow.server,
+                "state": flow_state(result),
+                "error": flow_error(result),
+                "job": result.job().map(|job| json(None, job)).unwrap_or(Value::Null),
+            })).collect::<Vec<_>>(),
+            "summary": {
+                "flows": summary.flows,
+                "completed": summary.completed,
+                "messages": summary.totals.messages,
+                "bytes": summary.totals.bytes,
+                "bandwidthGbps": summary.totals.bandwidth_gbps,
+                "latency": summary.latency.as_ref().map(|l| json!({
+                    "count": l.count,
+                    "p50Us": l.p50_us,
+                    "p99Us": l.p99_us,
+                    "p999Us": l.p999_us,
+                    "maxUs": l.max_us,
+                })).unwrap_or(Value::Null),
+            },
+        }).to_string(),
+        // one row per flow and a last row with flow "total" for the aggregate
+        Format::Csv => {
+            let mut rows = Vec::new();
+            if header {
+                rows.push("flow,initiator,server,state,messages,bytes,bandwidth_gbps,p50_us,p99_us,p999_us,max_us,error".to_string());
+            }
+            for result in results {
+                let totals = result.job().map(scenario::totals).unwrap_or_default();
+                let l = flow_latency(result);
+                rows.push(format!("{},{},{},{},{},{},{},{},{},{},{},{}", result.flow.id, csv_escape(&result.initiator), csv_escape(&result.flow.server), flow_state(result), totals.messages, totals.bytes, totals.bandwidth_gbps, l.p50_us, l.p99_us, l.p999_us, l.max_us, csv_escape(&flow_error(result))));
+            }
+            let l = summary.latency.clone().unwrap_or_default();
+            rows.push(format!("total,,,{}/{},{},{},{},{},{},{},{},", summary.completed, summary.flows, summary.totals.messages, summary.totals.bytes, summary.totals.bandwidth_gbps, l.p50_us, l.p99_us, l.p999_us, l.max_us));
+            rows.join("\n")
+        },
+    }
+}
+
+// render_agents formats the inventory of a controller
+pub fn render_agents(format: &Format, agents: &[Agent], header: bool) -> String {
+    let agent_state = |agent: &Agent| AgentState::try_from(agent.state).map(|state| state.as_str_name()).unwrap_or("UNKNOWN");
+    let devices = |agent: &Agent| agent.capabilities.as_ref().map(|caps| caps.devices.iter().map(|d| d.name.clone()).collect::<Vec<_>>().join(" ")).unwrap_or_default();
+    let version = |agent: &Agent| agent.capabilities.as_ref().map(|caps| caps.version.clone()).unwrap_or_default();
+    match format {
+        Format::Table => {
+            let mut out = format!("{:>24} {:>12} {:>8} {:>8} {:>8} {:>22} devices", "agent", "state", "server", "running", "version", "last heartbeat ms");
+            for a in agents {
+                let _ = write!(out, "\n{:>24} {:>12} {:>8} {:>8} {:>8} {:>22} {}", a.id, agent_state(a), a.server_port, a.running_jobs, version(a), a.last_heartbeat_unix_ms, devices(a));
+            }
+            out
+        },
+        Format::Json => Value::from(agents.iter().map(|a| json!({
+            "id": a.id,
+            "address": a.address,
+            "serverPort": a.server_port,
+            "initiatorPort": a.initiator_port,
+            "state": agent_state(a),
+            "runningJobs": a.running_jobs,
+            "version": version(a),
+            "devices": devices(a),
+            "registeredUnixMs": a.registered_unix_ms,
+            "lastHeartbeatUnixMs": a.last_heartbeat_unix_ms,
+        })).collect::<Vec<_>>()).to_string(),
+        Format::Csv => {
+            let mut rows = Vec::new();
+            if header {
+                rows.push("agent,address,server_port,initiator_port,state,running_jobs,version,devices,registered_unix_ms,last_heartbeat_unix_ms".to_string());
+            }
+            rows.extend(agents.iter().map(|a| format!("{},{},{},{},{},{},{},{},{},{}", csv_escape(&a.id), csv_escape(&a.address), a.server_port, a.initiator_port, agent_state(a), a.running_jobs, csv_escape(&version(a)), csv_escape(&devices(a)), a.registered_unix_ms, a.last_heartbeat_unix_ms)));
+            rows.join("\n")
+        },
+    }
+}
+
+// render_dispatched formats a job dispatched by a controller, csv output
+// holds the rows of the job only
+pub fn render_dispatched(format: &Format, reply: Option<&SendReply>, job: &DispatchedJob, header: bool) -> String {
+    let status = job.status.clone().unwrap_or_default();
+    match format {
+        Format::Table => {
+            let mut out = format!("job {} from {} to {}", job.id, job.initiator, job.server);
+            if !job.error.is_empty() {
+                let _ = write!(out, "\ncontroller error: {}", job.error);
+            }
+            let _ = write!(out, "\n{}", table(reply, &status));
+            out
+        },
+        Format::Json => json!({
+            "id": job.id,
+            "initiator": job.initiator,
+            "server": job.server,
+            "error": job.error,
+            "job": json(reply, &status),
+        }).to_string(),
+        Format::Csv => csv(&status, header),
+    }
+}
+
+// render_dispatched_jobs formats the jobs of a controller
+pub fn render_dispatched_jobs(format: &Format, jobs: &[DispatchedJob], header: bool) -> String {
+    let dispatched_state = |job: &DispatchedJob| job.status.as_ref().map_or("UNKNOWN", state);
+    let dispatched_error = |job: &DispatchedJob| if job.error.is_empty() {
+        job.status.as_ref().map(|status| status.error.clone()).unwrap_or_default()
+    } else {
+        job.error.clone()
+    };
+    match format {
+        Format::Table => {
+            let mut out = format!("{:>10} {:>24} {:>24} {:>14} error", "id", "initiator", "server", "state");
+            for job in jobs {
+                let _ = write!(out, "\n{:>10} {:>24} {:>24} {:>14} {}", job.id, job.initiator, job.server, dispatched_state(job), dispatched_error(job));
+            }
+            out
+        },
+        Format::Json => Value::from(jobs.iter().map(|job| json!({
+            "id": job.id,
+            "initiator": job.initiator,
+            "server": job.server,
+            "state": dispatched_state(job),
+            "error": dispatched_error(job),
+        })).collect::<Vec<_>>()).to_string(),
+        Format::Csv => {
+            let mut rows = Vec::new();
+            if header {
+                rows.push("id,initiator,server,state,error".to_string());
+            }
+            rows.extend(jobs.iter().map(|job| format!("{},{},{},{},{}", job.id, csv_escape(&job.initiator), csv_escape(&job.server), dispatched_state(job), csv_escape(&dispatched_error(job)))));
+            rows.join("\n")
+        },
+    }
+}
+
+// render_results formats stored results, one row per job with the totals
+// of its measurements
+pub fn render_results(format: &Format, results: &[StoredResult], header: bool) -> String {
+    let spec = |r: &StoredResult| r.spec.clone().unwrap_or_default();
+    let status = |r: &StoredResult| r.status.clone().unwrap_or_default();
+    let op = |r: &StoredResult| Operation::try_from(spec(r).op).map(|op| op.as_str_name()).unwrap_or("UNKNOWN");
+    let sizes = |r: &StoredResult| result_sizes(&spec(r), &status(r)).iter().map(|size| size.to_string()).collect::<Vec<_>>().join(" ");
+    let tags = |r: &StoredResult| spec(r).tags.join(" ");
+    match format {
+        Format::Table => {
+            let mut out = format!("{:>8} {:>16} {:>8} {:>22} {:>22} {:>14} {:>14} {:>10} {:>12} {:>10} {:>10} tags", "result", "finished ms", "job", "initiator", "server", "op", "state", "messages", "Gb/s", "p50 us", "p99 us");
+            for r in results {
+                let (spec, status) = (spec(r), status(r));
+                let totals = scenario::totals(&status);
+                let l = status.latency.clone().unwrap_or_default();
+                let _ = write!(out, "\n{:>8} {:>16} {:>8} {:>22} {:>22} {:>14} {:>14} {:>10} {:>12.3} {:>10.3} {:>10.3} {}", r.id, r.finished_unix_ms, spec.id, r.initiator, spec.address, op(r), state(&status), totals.messages, totals.bandwidth_gbps, l.p50_us, l.p99_us, tags(r));
+            }
+            out
+        },
+        Format::Json => Value::from(results.iter().map(|r| json!({
+            "id": r.id,
+            "finishedUnixMs": r.finished_unix_ms,
+            "initiator": r.initiator,
+            "server": spec(r).address,
+            "op": op(r),
+            "messageSizes": result_sizes(&spec(r), &status(r)),
+            "tags": spec(r).tags,
+            "job": json(None, &status(r)),
+        })).collect::<Vec<_>>()).to_string(),
+        Format::Csv => {
+            let mut rows = Vec::new();
+            if header {
+                rows.push("result,finished_unix_ms,job,initiator,server,op,message_sizes,state,messages,bytes,bandwidth_gbps,p50_us,p99_us,p999_us,max_us,tags".to_string());
+            }
+            for r in results {
+                let (spec, status) = (spec(r), status(r));
+                let totals = scenario::totals(&status);
+                let l = status.latency.clone().unwrap_or_default();
+                rows.push(format!("{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}", r.id, r.finished_unix_ms, spec.id, csv_escape(&r.initiator), csv_escape(&spec.address), op(r), sizes(r), state(&status), totals.messages, totals.bytes, totals.bandwidth_gbps, l.p50_us, l.p99_us, l.p999_us, l.max_us, csv_escape(&tags(r))));
+            }
+            rows.join("\n")
+        },
+    }
+}
+
+// result_sizes are the measured message sizes of a job, or the requested
+// one if it measured none
+fn result_sizes(spec: &SendRequest, job: &JobStatus) -> Vec<u32> {
+    let mut sizes = job.measurements.iter().map(|m| m.message_size).chain(job.cells.iter().map(|c| c.message_size)).collect::<Vec<_>>();
+    sizes.sort_unstable();
+    sizes.dedup();
+    if sizes.is_empty() {
+        sizes.push(spec.message_size);
+    }
+    sizes
+}
+
+fn job_state(state: i32) -> &'static str {
+    JobState::try_from(state).map(|state| state.as_str_name()).unwrap_or("UNKNOWN")
+}
+
+fn state(job: &JobStatus) -> &'static str {
+    job_state(job.state)
+}
+
+fn mtu(mtu: i32) -> &'static str {
+    Mtu::try_from(mtu).map(|mtu| mtu.as_str_name()).unwrap_or("UNKNOWN")
+}
+
+fn table(reply: Option<&SendReply>, job: &JobStatus) -> String {
+    let mut out = String::new();
+    if let Some(reply) = reply {
+        let _ = writeln!(out, "reply: {}", reply.message);
+    }
+    let _ = writeln!(out, "job {} {}", job.id, state(job));
+    if !job.error.is_empty() {
+        let _ = writeln!(out, "error: {}", job.error);
+    }
+    if !job.measurements.is_empty() {
+        let _ = writeln!(out, "{:>12} {:>10} {:>12} {:>12} {:>10} {:>10} {:>10} {:>10}", "size", "messages", "Gb/s", "us/msg", "p50 us", "p99 us", "p99.9 us", "max us");
+        for m in &job.measurements {
+            let l = m.latency.clone().unwrap_or_default();
+            let _ = writeln!(out, "{:>12} {:>10} {:>12.3} {:>12.3} {:>10.3} {:>10.3} {:>10.3} {:>10.3}", m.message_size, m.messages, m.bandwidth_gbps, m.time_per_message_us, l.p50_us, l.p99_us, l.p999_us, l.max_us);
+        }
+    }
+    if !job.cells.is_empty() {
+        let _ = writeln!(out, "{:>8} {:>12} {:>6} {:>4} {:>7} {:>12} {:>10} {:>12} {:>10}", "mtu", "size", "depth", "qps", "trials", "Gb/s", "stddev", "us/msg", "stddev");
+        for c in &job.cells {
+            let b = c.bandwidth_gbps.clone().unwrap_or_default();
+            let l = c.time_per_message_us.clone().unwrap_or_default();
+            let _ = writeln!(out, "{:>8} {:>12} {:>6} {:>4} {:>7} {:>12.3} {:>10.3} {:>12.3} {:>10.3}", mtu(c.mtu), c.message_size, c.tx_depth, c.connections, c.trials.len(), b.mean, b.stddev, l.mean, l.stddev);
+        }
+    }
+    if !job.intervals.is_empty() {
+        let _ = writeln!(out, "{:>17} {:>12} {:>12} {:>8}", "interval s", "Gb/s", "msg/s", "errors");
+        for i in &job.intervals {
+            let start = i.start_ns as f64 / 1e9;
+            let end = (i.start_ns + i.elapsed_ns) as f64 / 1e9;
+            let _ = writeln!(out, "{:>8.1}-{:<8.1} {:>12.3} {:>12.0} {:>8}", start, end, i.bandwidth_gbps, i.message_rate, i.errors);
+        }
+    }
+    for (name, latency) in [("latency", &job.latency), ("server receive wait", &job.server_receive_wait)] {
+        if let Some(l) = latency {
+            let _ = writeln!(out, "{}: {} messages, p50 {:.3} us, p99 {:.3} us, p99.9 {:.3} us, max {:.3} us", name, l.count, l.p50_us, l.p99_us, l.p999_us, l.max_us);
+        }
+    }
+    if let Some(d) = &job.delivery {
+        let _ = writeln!(out, "delivery: {} sent, {} received, {} receive errors, {} integrity errors", d.sent, d.received, d.receive_errors, d.integrity_errors);
+    }
+    out.trim_end().to_string()
+}
+
+fn latency_json(latency: &Option<Latency>) -> Value {
+    match latency {
+        Some(l) => json!({
+            "count": l.count,
+            "p50Us": l.p50_us,
+            "p99Us": l.p99_us,
+            "p999Us": l.p999_us,
+            "maxUs": l.max_us,
+            // base64 of the V2 deflate encoded HDR histogram, for merging
+            "histogram": STANDARD.encode(&l.histogram),
+        }),
+        None => Value::Null,
+    }
+}
+
+fn measurement_json(m: &Measurement) -> Value {
+    json!({
+        "messageSize": m.message_size,
+        "messages": m.messages,
+        "bytes": m.bytes,
+        "elapsedNs": m.elapsed_ns,
+        "bandwidthGbps": m.bandwidth_gbps,
+        "timePerMessageUs": m.time_per_message_us,
+        "latency": latency_json(&m.latency),
+    })
+}
+
+fn cell_json(c: &Cell) -> Value {
+    let stats = |s: &Option<rocky_rs::listener::listener::Stats>| match s {
+        Some(s) => json!({
+            "mean": s.mean,
+            "stddev": s.stddev,
+            "min": s.min,
+            "max": s.max,
+            "ci95": s.ci95,
+        }),
+        None => Value::Null,
+    };
+    json!({
+        "mtu": mtu(c.mtu),
+        "messageSize": c.message_size,
+        "txDepth": c.tx_depth,
+        "connections": c.connections,
+        "trials": c.trials.iter().map(measurement_json).collect::<Vec<_>>(),
+        "bandwidthGbps": stats(&c.bandwidth_gbps),
+        "timePerMessageUs": stats(&c.time_per_message_us),
+        "latency": latency_json(&c.latency),
+    })
+}
+
+fn delivery_json(delivery: &Option<Delivery>) -> Value {
+    match delivery {
+        Some(d) => json!({
+            "sent": d.sent,
+            "received": d.received,
+            "receiveErrors": d.receive_errors,
+            "integrityErrors": d.integrity_errors,
+        }),
+        None => Value::Null,
+    }
+}
+
+fn interval_json(i: &Interval) -> Value {
+    json!({
+        "startNs": i.start_ns,
+        "elapsedNs": i.elapsed_ns,
+        "messages": i.messages,
+        "bytes": i.bytes,
+        "bandwidthGbps": i.bandwidth_gbps,
+        "messageRate": i.message_rate,
+        "errors": i.errors,
+    })
+}
+
+fn json(reply: Option<&SendReply>, job: &JobStatus) -> Value {
+    let mut value = json!({
+        "id": job.id,
+        "state": state(job),
+        "error": job.error,
+        "measurements": job.measurements.iter().map(measurement_json).collect::<Vec<_>>(),
+        "cells": job.cells.iter().map(cell_json).collect::<Vec<_>>(),
+        "intervals": job.intervals.iter().map(interval_json).collect::<Vec<_>>(),
+        "latency": latency_json(&job.latency),
+        "serverReceiveWait": latency_json(&job.server_receive_wait),
+        "delivery": delivery_json(&job.delivery),
+    });
+    if let Some(reply) = reply {
+        value["reply"] = reply_json(reply);
+    }
+    value
+}
+
+fn reply_json(reply: &SendReply) -> Value {
+    json!({
+        "message": reply.message,
+        "trafficClass": reply.traffic_class,
+        "serviceLevel": reply.service_level,
+        "flowLabel": reply.flow_label,
+    })
+}
+
+const CSV_HEADER: &str = "record,id,state,mtu,message_size,tx_depth,connections,trial,start_ns,elapsed_ns,messages,bytes,bandwidth_gbps,message_rate,time_per_message_us,p50_us,p99_us,p999_us,max_us,errors,sent,received,receive_errors,integrity_errors,error";
+
+// CsvRow is one line of csv output, records of different kinds share the
+// columns and leave the ones that don't apply empty
+#[derive(Default)]
+struct CsvRow {
+    record: &'static str,
+    mtu: String,
+    message_size: String,
+    tx_depth: String,
+    connections: String,
+    trial: String,
+    start_ns: String,
+    elapsed_ns: String,
+    messages: String,
+    bytes: String,
+    bandwidth_gbps: String,
+    message_rate: String,
+    time_per_message_us: String,
+    latency: Option<Latency>,
+    errors: String,
+    sent: String,
+    received: String,
+    receive_errors: String,
+    integrity_errors: String,
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn measurement_row(record: &'static str, m: &Measurement) -> CsvRow {
+    CsvRow{
+        record,
+        message_size: m.message_size.to_string(),
+        elapsed_ns: m.elapsed_ns.to_string(),
+        messages: m.messages.to_string(),
+        bytes: m.bytes.to_string(),
+        bandwidth_gbps: m.bandwidth_gbps.to_string(),
+        time_per_message_us: m.time_per_message_us.to_string(),
+        latency: m.latency.clone(),
+        ..Default::default()
+    }
+}
+
+fn csv(job: &JobStatus, header: bool) -> String {
+    let mut rows = Vec::new();
+    rows.push(CsvRow{
+        record: "job",
+        latency: job.latency.clone(),
+        ..Default::default()
+    });
+    if let Some(latency) = &job.server_receive_wait {
+        rows.push(CsvRow{
+            record: "server",
+            latency: Some(latency.clone()),
+            ..Default::default()
+        });
+    }
+    if let Some(d) = &job.delivery {
+        rows.push(CsvRow{
+            record: "delivery",
+            sent: d.sent.to_string(),
+            received: d.received.to_string(),
+            receive_errors: d.receive_errors.to_string(),
+            integrity_errors: d.integrity_errors.to_string(),
+            ..Default::default()
+        });
+    }
+    rows.extend(job.measurements.iter().map(|m| measurement_row("measurement", m)));
+    for c in &job.cells {
+        for (i, m) in c.trials.iter().enumerate() {
+            rows.push(CsvRow{
+                mtu: mtu(c.mtu).to_string(),
+                tx_depth: c.tx_depth.to_string(),
+                connections: c.connections.to_string(),
+                trial: i.to_string(),
+                ..measurement_row("trial", m)
+            });
+        }
+    }
+    rows.extend(job.intervals.iter().map(|i| CsvRow{
+        record: "interval",
+        start_ns: i.start_ns.to_string(),
+        elapsed_ns: i.elapsed_ns.to_string(),
+        messages: i.messages.to_string(),
+        bytes: i.bytes.to_string(),
+        bandwidth_gbps: i.bandwidth_gbps.to_string(),
+        message_rate: i.message_rate.to_string(),
+        errors: i.errors.to_string(),
+        ..Default::default()
+    }));
+    let mut out = String::new();
+    if header {
+        let _ = writeln!(out, "{}", CSV_HEADER);
+    }
+    for row in rows {
+        let latency = |f: fn(&Latency) -> f64| row.latency.as_ref().map(|l| f(l).to_string()).unwrap_or_default();
+        let _ = writeln!(out, "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            row.record, job.id, state(job), row.mtu, row.message_size, row.tx_depth, row.connections, row.trial,
+            row.start_ns, row.elapsed_ns, row.messages, row.bytes, row.bandwidth_gbps, row.message_rate, row.time_per_message_us,
+            latency(|l| l.p50_us), latency(|l| l.p99_us), latency(|l| l.p999_us), latency(|l| l.max_us),
+            row.errors, row.sent, row.received, row.receive_errors, row.integrity_errors, csv_escape(&job.error));
+    }
+    out.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_rows_have_every_column() {
+        let job = JobStatus{
+            id: 1,
+            delivery: Some(Delivery{ sent: 10, received: 9, receive_errors: 1, integrity_errors: 2 }),
+            measurements: vec![Measurement::default()],
+            ..Default::default()
+        };
+        let out = csv(&job, true);
+        let columns = CSV_HEADER.split(',').count();
+        assert!(out.lines().all(|line| line.split(',').count() == columns), "{}", out);
+        let header = CSV_HEADER.split(',').collect::<Vec<_>>();
+        let delivery = out.lines().find(|line| line.starts_with("delivery,")).unwrap().split(',').collect::<Vec<_>>();
+        let column = |name: &str| delivery[header.iter().position(|h| *h == name).unwrap()];
+        assert_eq!((column("sent"), column("received"), column("receive_errors"), column("integrity_errors")), ("10", "9", "1", "2"));
+    }
+}
@@ -0,0 +1 @@
+pub mod pattern;
\ No newline at end of file
@@ -0,0 +1,191 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+use crate::scenario::scenario::{Flow, Scenario};
+
+// Pattern is a collective traffic pattern between the hosts of a fleet,
+// every flow of a pattern sends from the initiator of one host to the
+// server of another
+#[derive(Debug, Clone, Copy)]
+pub enum Pattern {
+    // every host sends to the target
+    Incast,
+    // every host sends to every other host
+    AllToAll,
+    // every host sends to the next one
+    Ring,
+    // every host sends to one other host and receives from one, picked at
+    // random
+    Permutation,
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "incast" => Ok(Pattern::Incast),
+            "all_to_all" => Ok(Pattern::AllToAll),
+            "ring" => Ok(Pattern::Ring),
+            "permutation" => Ok(Pattern::Permutation),
+            _ => Err("invalid pattern".to_string()),
+        }
+    }
+}
+
+impl Pattern {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pattern::Incast => "incast",
+            Pattern::AllToAll => "all_to_all",
+            Pattern::Ring => "ring",
+            Pattern::Permutation => "permutation",
+        }
+    }
+}
+
+// Fleet is a set of hosts that all run rocky-rs on the same ports
+pub struct Fleet {
+    pub hosts: Vec<String>,
+    pub initiator_port: u16,
+    pub server_port: u16,
+}
+
+// pairs returns the sending and receiving host of every flow of a pattern
+// over hosts distinct hosts
+pub fn pairs(pattern: Pattern, hosts: usize, target: usize, seed: u64) -> Vec<(usize, usize)> {
+    match pattern {
+        Pattern::Incast => (0..hosts).filter(|h| *h != target).map(|h| (h, target)).collect(),
+        Pattern::AllToAll => (0..hosts).flat_map(|from| (0..hosts).filter(move |to| *to != from).map(move |to| (from, to))).collect(),
+        Pattern::Ring => (0..hosts).map(|h| (h, (h + 1) % hosts)).collect(),
+        Pattern::Permutation => derangement(hosts, seed).into_iter().enumerate().collect(),
+    }
+}
+
+// derangement shuffles the hosts until none of them maps to itself, which
+// takes e shuffles on average
+fn derangement(hosts: usize, seed: u64) -> Vec<usize> {
+    let mut state = seed;
+    loop {
+        let mut targets = (0..hosts).collect::<Vec<_>>();
+        for i in (1..hosts).rev() {
+            let j = (splitmix64(&mut state) % (i as u64 + 1)) as usize;
+            targets.swap(i, j);
+        }
+        if targets.iter().enumerate().all(|(from, to)| from != *to) {
+            return targets;
+        }
+    }
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+// scenario expands a pattern into one flow per host pair, the flows copy
+// template and number their job ids up from the id of the template and are
+// tagged with the pattern. They all start start_delay seconds after they are
+// submitted
+pub fn scenario(pattern: Pattern, fleet: &Fleet, target: Option<&str>, seed: u64, template: &Flow, start_delay: f64) -> Result<Scenario, String> {
+    if fleet.hosts.len() < 2 {
+        return Err(format!("{} needs at least two hosts", pattern.name()));
+    }
+    let mut seen = HashSet::new();
+    if let Some(host) = fleet.hosts.iter().find(|host| !seen.insert(host.as_str())) {
+        return Err(format!("host {} is listed more than once", host));
+    }
+    let target = match target {
+        Some(target) => fleet.hosts.iter().position(|host| host == target).ok_or_else(|| format!("target {} is not one of the hosts", target))?,
+        None => 0,
+    };
+    let pairs = pairs(pattern, fleet.hosts.len(), target, seed);
+    let mut tags = template.tags.clone();
+    tags.push(format!("pattern={}", pattern.name()));
+    let mut flows = Vec::with_capacity(pairs.len());
+    for (i, (from, to)) in pairs.into_iter().enumerate() {
+        let id = u32::try_from(i).ok().and_then(|i| template.id.checked_add(i)).
+            ok_or_else(|| format!("{} flows from job id {} overflow the job ids", flows.capacity(), template.id))?;
+        flows.push(Flow{
+            id,
+            initiator: Some(format!("{}:{}", fleet.hosts[from], fleet.initiator_port)),
+            server: format!("{}:{}", fleet.hosts[to], fleet.server_port),
+            tags: tags.clone(),
+            ..template.clone()
+        });
+    }
+    let name = match pattern {
+        Pattern::Incast => format!("incast to {}", fleet.hosts[target]),
+        Pattern::Permutation => format!("permutation with seed {}", seed),
+        _ => pattern.name().to_string(),
+    };
+    let scenario = Scenario{
+        name,
+        start_delay: Some(start_delay),
+        flows,
+    };
+    scenario.validate()?;
+    Ok(scenario)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pair_counts() {
+        for hosts in 2..8 {
+            assert_eq!(pairs(Pattern::Incast, hosts, 1, 0).len(), hosts - 1);
+            assert_eq!(pairs(Pattern::Ring, hosts, 0, 0).len(), hosts);
+            assert_eq!(pairs(Pattern::AllToAll, hosts, 0, 0).len(), hosts * (hosts - 1));
+            assert_eq!(pairs(Pattern::Permutation, hosts, 0, 7).len(), hosts);
+        }
+    }
+
+    #[test]
+    fn incast_targets_the_target() {
+        let pairs = pairs(Pattern::Incast, 5, 3, 0);
+        assert!(pairs.iter().all(|(from, to)| *to == 3 && *from != 3));
+    }
+
+    #[test]
+    fn no_host_sends_to_itself() {
+        for pattern in [Pattern::Incast, Pattern::AllToAll, Pattern::Ring, Pattern::Permutation] {
+            for hosts in 2..8 {
+                assert!(pairs(pattern, hosts, 0, 42).iter().all(|(from, to)| from != to), "{} over {} hosts", pattern.name(), hosts);
+            }
+        }
+    }
+
+    #[test]
+    fn derangement_has_no_fixed_points() {
+        for hosts in 2..16 {
+            for seed in 0..64 {
+                let mut targets = derangement(hosts, seed);
+                assert!(targets.iter().enumerate().all(|(from, to)| from != *to));
+                targets.sort_unstable();
+                assert_eq!(targets, (0..hosts).collect::<Vec<_>>());
+            }
+        }
+    }
+
+    fn fleet(hosts: &[&str]) -> Fleet {
+        Fleet{
+            hosts: hosts.iter().map(|host| host.to_string()).collect(),
+            initiator_port: 50052,
+            server_port: 50051,
+        }
+    }
+
+    #[test]
+    fn duplicate_hosts_are_rejected() {
+        let template: Flow = toml::from_str("id = 1\nserver = \"\"\n").unwrap();
+        for pattern in [Pattern::Incast, Pattern::AllToAll, Pattern::Ring, Pattern::Permutation] {
+            let err = scenario(pattern, &fleet(&["a", "b", "a"]), None, 0, &template, 1.0).unwrap_err();
+            assert!(err.contains("host a"), "{}", err);
+            let scenario = scenario(pattern, &fleet(&["a", "b", "c"]), None, 0, &template, 1.0).unwrap();
+            assert!(scenario.flows.iter().all(|flow| flow.initiator.as_deref().unwrap().split(':').next() != flow.server.split(':').next()));
+        }
+    }
+}
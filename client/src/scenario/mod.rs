@@ -0,0 +1 @@
+pub mod scenario;
\ No newline at end of file
@@ -0,0 +1,387 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use byte_unit::Byte;
+use hdrhistogram::Histogram;
+use hdrhistogram::serialization::Deserializer;
+use rocky_rs::listener::listener::{
+    listener_client::ListenerClient,
+    ConnectionType,
+    ImmMode,
+    JobRequest,
+    JobStatus,
+    Latency,
+    Matrix,
+    Mtu,
+    Operation,
+    SendRequest,
+};
+use serde::Deserialize;
+use crate::{ClientConnectionType, ClientOperation, MtuSize, EXIT_TIMEOUT};
+
+// Scenario is a set of flows that run together, every flow becomes one job
+// on its initiator. Job ids identify the flows on the servers as well, so
+// they must be unique within a scenario
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    #[serde(default)]
+    pub name: String,
+    // seconds between submitting the flows and a start time the initiators
+    // share, the start offsets of the flows count from it. Without it every
+    // flow is submitted at its offset and starts sending once connected
+    pub start_delay: Option<f64>,
+    pub flows: Vec<Flow>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Flow {
+    pub id: u32,
+    // host:port of the initiator, the --initiator of the client if unset
+    pub initiator: Option<String>,
+    // host:port of the server
+    pub server: String,
+    #[serde(default = "default_op")]
+    pub op: String,
+    // message sizes like "64KiB", more than one runs a sweep over them
+    #[serde(default)]
+    pub sizes: Vec<String>,
+    #[serde(default = "default_messages")]
+    pub messages: u32,
+    // messages per second on every queue pair, 0 is unlimited
+    #[serde(default)]
+    pub rate: u32,
+    // seconds after the start of the scenario the flow starts. With the
+    // scenario's start_delay it starts sending at that offset from the
+    // shared start time, without it the offset only delays its submission
+    // and it starts sending whenever it is connected
+    #[serde(default)]
+    pub start: f64,
+    #[serde(default = "default_qps")]
+    pub qps: u32,
+    pub mtu: Option<u32>,
+    pub tx_depth: Option<u32>,
+    pub connection_type: Option<String>,
+    // labels like "rack=a3" kept with the results of the flow
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+fn default_op() -> String {
+    "send".to_string()
+}
+
+fn default_messages() -> u32 {
+    1
+}
+
+fn default_qps() -> u32 {
+    1
+}
+
+// load reads a scenario from a yaml or toml file, picked by its extension
+pub fn load(path: &Path) -> Result<Scenario, Box<dyn std::error::Error>> {
+    let content = std::fs::read_to_string(path)?;
+    let scenario: Scenario = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("yaml") | Some("yml") => serde_yaml::from_str(&content)?,
+        Some("toml") => toml::from_str(&content)?,
+        _ => return Err(format!("{}: scenario files must end in .yaml, .yml or .toml", path.display()).into()),
+    };
+    scenario.validate().map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(scenario)
+}
+
+fn valid_offset(secs: f64) -> bool {
+    secs.is_finite() && secs >= 0.0
+}
+
+impl Scenario {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.flows.is_empty() {
+            return Err("scenario has no flows".to_string());
+        }
+        if let Some(start_delay) = self.start_delay {
+            if !valid_offset(start_delay) {
+                return Err(format!("invalid start delay {}", start_delay));
+            }
+        }
+        let mut ids = HashSet::new();
+        for flow in &self.flows {
+            if !ids.insert(flow.id) {
+                return Err(format!("flow id {} is used more than once", flow.id));
+            }
+            if !valid_offset(flow.start) {
+                return Err(format!("flow {} has an invalid start offset {}", flow.id, flow.start));
+            }
+            flow.request()?;
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_size(size: &str) -> Result<u32, String> {
+    let bytes = Byte::parse_str(size, true).map_err(|e| format!("invalid size {}: {}", size, e))?.as_u64();
+    u32::try_from(bytes).map_err(|_| format!("size {} is too large", size))
+}
+
+impl Flow {
+    // request builds the job of the flow, several queue pairs run as a
+    // matrix with a single cell
+    pub fn request(&self) -> Result<SendRequest, String> {
+        let op: ClientOperation = self.op.parse().map_err(|e| format!("flow {}: {}", self.id, e))?;
+        let mtu = match self.mtu {
+            Some(mtu) => Mtu::from(mtu.to_string().parse::<MtuSize>().map_err(|e| format!("flow {}: {}", self.id, e))?),
+            None => Mtu::Mtu1024,
+        };
+        let connection_type = match &self.connection_type {
+            Some(connection_type) => ConnectionType::from(connection_type.parse::<ClientConnectionType>().map_err(|e| format!("flow {}: {}", self.id, e))?),
+            None => ConnectionType::RcTcp,
+        };
+        let sizes = self.sizes.iter().map(|size| parse_size(size)).collect::<Result<Vec<_>, _>>().map_err(|e| format!("flow {}: {}", self.id, e))?;
+        if self.qps == 0 {
+            return Err(format!("flow {}: qps must be greater than 0", self.id));
+        }
+        if self.qps > 1 && sizes.len() > 1 {
+            return Err(format!("flow {}: several sizes and several qps are mutually exclusive", self.id));
+        }
+        let matrix = if self.qps > 1 {
+            Some(Matrix{
+                connections: vec![self.qps],
+                ..Default::default()
+            })
+        } else {
+            None
+        };
+        Ok(SendRequest{
+            id: self.id,
+            address: self.server.clone(),
+            op: Operation::from(op).into(),
+            message_size: sizes.first().copied().unwrap_or(8),
+            messages: self.messages,
+            mtu: mtu.into(),
+            imm_mode: ImmMode::ImmConstant.into(),
+            imm_data: 1,
+            connection_type: connection_type.into(),
+            sweep_sizes: if sizes.len() > 1 { sizes } else { Vec::new() },
+            tx_depth: self.tx_depth.unwrap_or(1),
+            matrix,
+            repetitions: 1,
+            message_rate: self.rate,
+            tags: self.tags.clone(),
+            ..Default::default()
+        })
+    }
+}
+
+pub enum Outcome {
+    Finished(JobStatus),
+    // the last status seen before the wait timed out
+    TimedOut(Option<JobStatus>),
+    Failed(String),
+}
+
+pub struct FlowResult {
+    pub flow: Flow,
+    pub initiator: String,
+    pub outcome: Outcome,
+}
+
+impl FlowResult {
+    pub fn job(&self) -> Option<&JobStatus> {
+        match &self.outcome {
+            Outcome::Finished(job) => Some(job),
+            Outcome::TimedOut(job) => job.as_ref(),
+            Outcome::Failed(_) => None,
+        }
+    }
+}
+
+// run submits every flow at its start offset to its initiator and waits for
+// all of them, timeout limits the wait of every flow after its submission
+pub async fn run(scenario: &Scenario, initiator: Option<&str>, timeout: Option<u64>) -> Vec<FlowResult> {
+    let start_at = scenario.start_delay.map(|delay| SystemTime::now() + Duration::from_secs_f64(delay));
+    let mut tasks = Vec::with_capacity(scenario.flows.len());
+    for flow in &scenario.flows {
+        let flow = flow.clone();
+        let initiator = flow.initiator.clone().or(initiator.map(String::from));
+        tasks.push(tokio::spawn(async move {
+            let (initiator, outcome) = match initiator {
+                Some(initiator) => {
+                    let outcome = run_flow(&flow, &initiator, start_at, timeout).await;
+                    (initiator, outcome)
+                },
+                None => (String::new(), Outcome::Failed("no initiator in the flow and no --initiator".to_string())),
+            };
+            FlowResult{ flow, initiator, outcome }
+        }));
+    }
+    let mut results = Vec::with_capacity(tasks.len());
+    for (task, flow) in tasks.into_iter().zip(&scenario.flows) {
+        results.push(task.await.unwrap_or_else(|e| FlowResult{
+            flow: flow.clone(),
+            initiator: String::new(),
+            outcome: Outcome::Failed(e.to_string()),
+        }));
+    }
+    results
+}
+
+async fn run_flow(flow: &Flow, initiator: &str, start_at: Option<SystemTime>, timeout: Option<u64>) -> Outcome {
+    let mut request = match flow.request() {
+        Ok(request) => request,
+        Err(e) => return Outcome::Failed(e),
+    };
+    let offset = Duration::from_secs_f64(flow.start);
+    match start_at {
+        Some(start_at) => {
+            let start_at = (start_at + offset).duration_since(UNIX_EPOCH).unwrap_or_default();
+            request.start_at_unix_ns = start_at.as_nanos() as u64;
+        },
+        None => tokio::time::sleep(offset).await,
+    }
+    let mut client = match ListenerClient::connect(format!("http://{}", initiator)).await {
+        Ok(client) => client,
+        Err(e) => return Outcome::Failed(format!("failed to connect to {}: {}", initiator, e)),
+    };
+    if let Err(status) = client.send(request).await {
+        return Outcome::Failed(status.message().to_string());
+    }
+    match crate::wait_for(&mut client, flow.id, timeout).await {
+        Ok(Some(job)) => Outcome::Finished(job),
+        Ok(None) => Outcome::TimedOut(client.get_job(JobRequest{ id: flow.id }).await.ok().map(|r| r.into_inner())),
+        Err(status) => Outcome::Failed(status.message().to_string()),
+    }
+}
+
+// exit_code is the worst exit code of the flows
+pub fn exit_code(results: &[FlowResult]) -> u8 {
+    results.iter().map(|result| match &result.outcome {
+        Outcome::Finished(job) => crate::exit_code(job),
+        Outcome::TimedOut(_) => EXIT_TIMEOUT,
+        Outcome::Failed(_) => 1,
+    }).max().unwrap_or(0)
+}
+
+// Totals sums the measured messages of a job, the bandwidth is the one of
+// the job while it was sending
+#[derive(Default)]
+pub struct Totals {
+    pub messages: u64,
+    pub bytes: u64,
+    pub bandwidth_gbps: f64,
+}
+
+pub fn totals(job: &JobStatus) -> Totals {
+    let measurements = job.measurements.iter().chain(job.cells.iter().flat_map(|c| c.trials.iter()));
+    let (mut messages, mut bytes, mut elapsed_ns) = (0, 0, 0);
+    for m in measurements {
+        messages += m.messages as u64;
+        bytes += m.bytes;
+        elapsed_ns += m.elapsed_ns;
+    }
+    Totals{
+        messages,
+        bytes,
+        bandwidth_gbps: if elapsed_ns > 0 { bytes as f64 * 8.0 / elapsed_ns as f64 } else { 0.0 },
+    }
+}
+
+// Summary aggregates the flows of a scenario. The bandwidth is the bytes of
+// all flows over the wall clock window from the first flow starting to send
+// to the last one sending its last message
+pub struct Summary {
+    pub flows: usize,
+    pub completed: usize,
+    pub totals: Totals,
+    pub latency: Option<Latency>,
+}
+
+pub fn summarize(results: &[FlowResult]) -> Summary {
+    let mut summary = Summary{
+        flows: results.len(),
+        completed: 0,
+        totals: Totals::default(),
+        latency: None,
+    };
+    let mut histogram: Option<Histogram<u64>> = None;
+    let mut deserializer = Deserializer::new();
+    let mut window: Option<(u64, u64)> = None;
+    for job in results.iter().filter_map(|result| result.job()) {
+        if crate::classify(job).0 == 0 {
+            summary.completed += 1;
+        }
+        let totals = totals(job);
+        summary.totals.messages += totals.messages;
+        summary.totals.bytes += totals.bytes;
+        if job.started_unix_ns > 0 && job.sent_unix_ns >= job.started_unix_ns {
+            window = Some(match window {
+                Some((start, end)) => (start.min(job.started_unix_ns), end.max(job.sent_unix_ns)),
+                None => (job.started_unix_ns, job.sent_unix_ns),
+            });
+        }
+        let encoded = match &job.latency {
+            Some(latency) if !latency.histogram.is_empty() => &latency.histogram,
+            _ => continue,
+        };
+        let flow_histogram: Histogram<u64> = match deserializer.deserialize(&mut encoded.as_slice()) {
+            Ok(flow_histogram) => flow_histogram,
+            Err(e) => {
+                eprintln!("job {}: invalid latency histogram: {}", job.id, e);
+                continue;
+            },
+        };
+        match histogram.as_mut() {
+            Some(histogram) => {
+                if let Err(e) = histogram.add(&flow_histogram) {
+                    eprintln!("job {}: failed to merge latencies: {}", job.id, e);
+                }
+            },
+            None => histogram = Some(flow_histogram),
+        }
+    }
+    if let Some((start, end)) = window.filter(|(start, end)| end > start) {
+        summary.totals.bandwidth_gbps = summary.totals.bytes as f64 * 8.0 / (end - start) as f64;
+    }
+    summary.latency = histogram.map(|histogram| {
+        let us = |ns: u64| ns as f64 / 1e3;
+        Latency{
+            histogram: Vec::new(),
+            count: histogram.len(),
+            p50_us: us(histogram.value_at_quantile(0.5)),
+            p99_us: us(histogram.value_at_quantile(0.99)),
+            p999_us: us(histogram.value_at_quantile(0.999)),
+            max_us: us(histogram.max()),
+        }
+    });
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scenario(ids: &[u32]) -> Scenario {
+        let flows = ids.iter().map(|id| format!("[[flows]]\nid = {}\nserver = \"10.0.0.2:50051\"\n", id)).collect::<String>();
+        toml::from_str(&flows).unwrap()
+    }
+
+    #[test]
+    fn validate_accepts_unique_ids() {
+        assert!(scenario(&[1, 2, 3]).validate().is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_duplicate_ids() {
+        let err = scenario(&[1, 2, 1]).validate().unwrap_err();
+        assert!(err.contains("flow id 1"), "{}", err);
+    }
+
+    #[test]
+    fn ud_flows_are_rejected() {
+        let mut scenario = scenario(&[1]);
+        scenario.flows[0].connection_type = Some("ud".to_string());
+        let err = scenario.flows[0].request().unwrap_err();
+        assert!(err.contains("ud is not supported"), "{}", err);
+    }
+}
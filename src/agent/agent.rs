@@ -0,0 +1,49 @@
+use std::time::Duration;
+use tonic::Request;
+use tracing::{error, info};
+use crate::initiator::initiator::initiator_capabilities;
+use crate::initiator::listener::listener::controller_client::ControllerClient;
+use crate::initiator::listener::listener::{HeartbeatRequest, RegisterRequest};
+use crate::jobs::jobs::Jobs;
+use crate::telemetry::telemetry;
+
+const RETRY_PERIOD: Duration = Duration::from_secs(2);
+
+// run registers the process with the controller and heartbeats until the
+// controller forgets it or can't be reached, then it registers again
+pub async fn run(controller: String, address: String, server_port: u16, initiator_port: u16, jobs: Jobs) {
+    let registration = RegisterRequest{
+        address,
+        server_port: server_port as u32,
+        initiator_port: initiator_port as u32,
+        capabilities: Some(initiator_capabilities()),
+    };
+    loop {
+        match session(&controller, &registration, &jobs).await {
+            Ok(()) => info!("controller {} forgot this agent, registering again", controller),
+            Err(e) => error!("controller {}: {}", controller, e),
+        }
+        tokio::time::sleep(RETRY_PERIOD).await;
+    }
+}
+
+// session registers once and heartbeats until the controller no longer
+// knows the agent
+async fn session(controller: &str, registration: &RegisterRequest, jobs: &Jobs) -> anyhow::Result<()> {
+    let mut client = ControllerClient::connect(format!("http://{}", controller)).await?;
+    let mut request = Request::new(registration.clone());
+    telemetry::inject(request.metadata_mut());
+    let reply = client.register(request).await?.into_inner();
+    info!("registered with controller {} as {}", controller, reply.agent_id);
+    let mut interval = tokio::time::interval(Duration::from_millis(reply.heartbeat_interval_ms.max(100) as u64));
+    loop {
+        interval.tick().await;
+        let heartbeat = HeartbeatRequest{
+            agent_id: reply.agent_id.clone(),
+            running_jobs: jobs.running() as u32,
+        };
+        if !client.heartbeat(heartbeat).await?.into_inner().registered {
+            return Ok(());
+        }
+    }
+}
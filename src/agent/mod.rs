@@ -0,0 +1 @@
+pub mod agent;
\ No newline at end of file
@@ -0,0 +1,74 @@
+use std::fs;
+use std::path::Path;
+use crate::initiator::listener::listener;
+use crate::server::connection_manager::connection_manager;
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+// largest message a single RC work request can carry
+pub const MAX_MESSAGE_SIZE: u32 = 1 << 31;
+// names match the ConnectionType enums
+pub const TRANSPORTS: [&str; 3] = ["RC_TCP", "RC_CM", "RC_GRPC"];
+pub const MTUS: [u32; 4] = [512, 1024, 2048, 4096];
+
+const SYSFS_INFINIBAND: &str = "/sys/class/infiniband";
+
+#[derive(Debug, Clone, Default)]
+pub struct Device {
+    pub name: String,
+    pub port: u32,
+    pub state: String,
+    pub link_layer: String,
+    pub rate: String,
+}
+
+// the initiator and the server report devices in their own Device messages
+macro_rules! impl_from_device {
+    ($($target:path),*) => {$(
+        impl From<Device> for $target {
+            fn from(d: Device) -> Self {
+                Self{
+                    name: d.name,
+                    port: d.port,
+                    state: d.state,
+                    link_layer: d.link_layer,
+                    rate: d.rate,
+                }
+            }
+        }
+    )*};
+}
+
+impl_from_device!(listener::Device, connection_manager::Device);
+
+// devices lists the ports of all rdma devices found in sysfs
+pub fn devices() -> Vec<Device> {
+    let mut devices = Vec::new();
+    let Ok(entries) = fs::read_dir(SYSFS_INFINIBAND) else {
+        return devices;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Ok(ports) = fs::read_dir(entry.path().join("ports")) else {
+            continue;
+        };
+        for port in ports.flatten() {
+            let Ok(port_num) = port.file_name().to_string_lossy().parse::<u32>() else {
+                continue;
+            };
+            let path = port.path();
+            devices.push(Device{
+                name: name.clone(),
+                port: port_num,
+                state: read_attr(&path, "state"),
+                link_layer: read_attr(&path, "link_layer"),
+                rate: read_attr(&path, "rate"),
+            });
+        }
+    }
+    devices.sort_by(|a, b| (&a.name, a.port).cmp(&(&b.name, b.port)));
+    devices
+}
+
+fn read_attr(path: &Path, attr: &str) -> String {
+    fs::read_to_string(path.join(attr)).map(|s| s.trim().to_string()).unwrap_or_default()
+}
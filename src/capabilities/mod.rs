@@ -0,0 +1 @@
+pub mod capabilities;
\ No newline at end of file
@@ -0,0 +1,418 @@
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tonic::{transport::{Channel, Server}, Code, Request, Response, Status};
+use tracing::{error, info, info_span, Instrument};
+use crate::initiator::listener::listener::controller_server::{self, ControllerServer};
+use crate::initiator::listener::listener::listener_client::ListenerClient;
+use crate::initiator::listener::listener::{
+    Agent, AgentList, AgentState, DispatchReply, DispatchRequest, DispatchedJob, DispatchedJobList,
+    HeartbeatReply, HeartbeatRequest, JobRequest, JobState, JobStatus, ListAgentsRequest, ListJobsRequest,
+    QueryResultsReply, QueryResultsRequest, RegisterReply, RegisterRequest, SendRequest,
+};
+use crate::metrics::metrics;
+use crate::telemetry::telemetry;
+use crate::store::store::Store;
+
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+// agents that missed this many heartbeats are lost
+const MISSED_HEARTBEATS: u32 = 3;
+// job ids from here up are picked by the controller, jobs dispatched with
+// an id or sent to an initiator directly stay below
+pub const CONTROLLER_JOB_IDS: u32 = 1 << 31;
+// ids the initiator already knows are skipped this many times
+const ID_ATTEMPTS: u32 = 16;
+// how long finished jobs stay listed
+const FINISHED_TTL: Duration = Duration::from_secs(600);
+
+pub struct Controller{
+    address: String,
+    agents: Agents,
+    jobs: DispatchedJobs,
+    store: Option<Store>,
+}
+
+impl Controller {
+    // collected jobs are kept in store if there is one
+    pub fn new(address: String, store: Option<Store>) -> Controller {
+        Controller{
+            address,
+            agents: Agents::default(),
+            jobs: DispatchedJobs::default(),
+            store,
+        }
+    }
+    // reserve records a job under an id its initiator doesn't know, the ids
+    // the controller picks are checked against the jobs of the initiator
+    async fn reserve(&self, client: &mut ListenerClient<Channel>, id: u32, initiator: &str, server: &str) -> Result<u32, Status> {
+        if id != 0 {
+            return self.jobs.reserve(id, initiator, server);
+        }
+        for _ in 0..ID_ATTEMPTS {
+            let id = self.jobs.reserve(0, initiator, server)?;
+            match client.get_job(JobRequest{ id }).await {
+                Err(status) if status.code() == Code::NotFound => return Ok(id),
+                Ok(_) => self.jobs.release(id),
+                Err(status) => {
+                    self.jobs.release(id);
+                    return Err(status);
+                },
+            }
+        }
+        Err(Status::resource_exhausted(format!("agent {} knows every job id tried", initiator)))
+    }
+    pub async fn run(self) -> anyhow::Result<()> {
+        info!("starting controller at {}", self.address);
+        let addr = self.address.parse()?;
+        Server::builder()
+            .add_service(ControllerServer::new(self))
+            .serve(addr)
+            .await?;
+        Ok(())
+    }
+}
+
+fn unix_ms(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+// Agents is the inventory of the registered agents, an agent is identified
+// by the address of its initiator
+#[derive(Clone, Default)]
+struct Agents {
+    agents: Arc<Mutex<HashMap<String, AgentEntry>>>,
+}
+
+struct AgentEntry {
+    agent: Agent,
+    last_heartbeat: Instant,
+}
+
+impl AgentEntry {
+    fn state(&self) -> AgentState {
+        if self.last_heartbeat.elapsed() > HEARTBEAT_INTERVAL * MISSED_HEARTBEATS {
+            AgentState::AgentLost
+        } else {
+            AgentState::AgentAlive
+        }
+    }
+}
+
+impl Agents {
+    #[allow(clippy::result_large_err)]
+    fn register(&self, request: RegisterRequest) -> Result<String, Status> {
+        if request.address.parse::<IpAddr>().is_ok_and(|ip| ip.is_unspecified()) {
+            return Err(Status::invalid_argument(format!("agent registered the unspecified address {}", request.address)));
+        }
+        let id = format!("{}:{}", request.address, request.initiator_port);
+        let now = SystemTime::now();
+        let agent = Agent{
+            id: id.clone(),
+            address: request.address,
+            server_port: request.server_port,
+            initiator_port: request.initiator_port,
+            capabilities: request.capabilities,
+            state: AgentState::AgentAlive.into(),
+            running_jobs: 0,
+            registered_unix_ms: unix_ms(now),
+            last_heartbeat_unix_ms: unix_ms(now),
+        };
+        self.agents.lock().unwrap().insert(id.clone(), AgentEntry{
+            agent,
+            last_heartbeat: Instant::now(),
+        });
+        Ok(id)
+    }
+    // heartbeat returns false for agents the controller doesn't know
+    fn heartbeat(&self, request: &HeartbeatRequest) -> bool {
+        match self.agents.lock().unwrap().get_mut(&request.agent_id) {
+            Some(entry) => {
+                entry.last_heartbeat = Instant::now();
+                entry.agent.last_heartbeat_unix_ms = unix_ms(SystemTime::now());
+                entry.agent.running_jobs = request.running_jobs;
+                true
+            },
+            None => false,
+        }
+    }
+    fn list(&self) -> Vec<Agent> {
+        let mut agents = self.agents.lock().unwrap().values().map(|entry| Agent{
+            state: entry.state().into(),
+            ..entry.agent.clone()
+        }).collect::<Vec<_>>();
+        agents.sort_by(|a, b| a.id.cmp(&b.id));
+        agents
+    }
+    // alive returns an agent jobs can be dispatched to
+    #[allow(clippy::result_large_err)]
+    fn alive(&self, id: &str) -> Result<Agent, Status> {
+        let agents = self.agents.lock().unwrap();
+        let entry = agents.get(id).ok_or_else(|| Status::not_found(format!("agent {} not found", id)))?;
+        if entry.state() != AgentState::AgentAlive {
+            return Err(Status::unavailable(format!("agent {} missed its heartbeats", id)));
+        }
+        Ok(entry.agent.clone())
+    }
+}
+
+// DispatchedJobs holds the latest status of every dispatched job until
+// FINISHED_TTL after it finished. Job ids are unique across the fleet,
+// servers tell the connections of different initiators apart by them
+#[derive(Clone)]
+struct DispatchedJobs {
+    jobs: Arc<Mutex<BTreeMap<u32, DispatchedEntry>>>,
+    // next id to pick, it starts at an offset of the clock so that a
+    // restarted controller doesn't pick the ids of its previous run again
+    next: Arc<AtomicU32>,
+}
+
+struct DispatchedEntry {
+    job: DispatchedJob,
+    finished: Option<Instant>,
+}
+
+impl Default for DispatchedJobs {
+    fn default() -> DispatchedJobs {
+        let offset = (unix_ms(SystemTime::now()) / 10 % CONTROLLER_JOB_IDS as u64) as u32;
+        DispatchedJobs{
+            jobs: Arc::default(),
+            next: Arc::new(AtomicU32::new(CONTROLLER_JOB_IDS + offset)),
+        }
+    }
+}
+
+impl DispatchedJobs {
+    // reserve records a job before it is sent to its initiator, an id of 0
+    // picks the next one from CONTROLLER_JOB_IDS up. Expired jobs are
+    // dropped first
+    #[allow(clippy::result_large_err)]
+    fn reserve(&self, id: u32, initiator: &str, server: &str) -> Result<u32, Status> {
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.retain(|_, entry| entry.finished.is_none_or(|finished| finished.elapsed() < FINISHED_TTL));
+        let id = if id == 0 {
+            let id = self.pick();
+            if jobs.contains_key(&id) {
+                return Err(Status::resource_exhausted(format!("job {} is still listed, the controller's ids wrapped around", id)));
+            }
+            id
+        } else if id >= CONTROLLER_JOB_IDS {
+            return Err(Status::invalid_argument(format!("job ids from {} up are picked by the controller", CONTROLLER_JOB_IDS)));
+        } else if jobs.contains_key(&id) {
+            return Err(Status::already_exists(format!("job {} was already dispatched", id)));
+        } else {
+            id
+        };
+        let job = DispatchedJob{
+            id,
+            initiator: initiator.to_string(),
+            server: server.to_string(),
+            status: Some(JobStatus{
+                id,
+                state: JobState::JobRunning.into(),
+                ..Default::default()
+            }),
+            error: String::new(),
+        };
+        jobs.insert(id, DispatchedEntry{ job, finished: None });
+        Ok(id)
+    }
+    // pick returns the next id of the controller's range, it counts up and
+    // wraps around to the start of the range
+    fn pick(&self) -> u32 {
+        let id = self.next.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |id| Some(id.checked_add(1).unwrap_or(CONTROLLER_JOB_IDS)));
+        id.unwrap_or(CONTROLLER_JOB_IDS)
+    }
+    fn release(&self, id: u32) {
+        self.jobs.lock().unwrap().remove(&id);
+    }
+    fn update(&self, id: u32, status: JobStatus) {
+        if let Some(entry) = self.jobs.lock().unwrap().get_mut(&id) {
+            entry.job.status = Some(status);
+        }
+    }
+    // finish starts the expiry of a job, error is set if the controller lost
+    // track of it
+    fn finish(&self, id: u32, error: Option<String>) {
+        if let Some(entry) = self.jobs.lock().unwrap().get_mut(&id) {
+            entry.finished = Some(Instant::now());
+            if let Some(error) = error {
+                entry.job.error = error;
+            }
+        }
+    }
+    fn get(&self, id: u32) -> Option<DispatchedJob> {
+        self.jobs.lock().unwrap().get(&id).map(|entry| entry.job.clone())
+    }
+    fn list(&self) -> Vec<DispatchedJob> {
+        self.jobs.lock().unwrap().values().map(|entry| entry.job.clone()).collect()
+    }
+}
+
+// collect follows a dispatched job on its initiator and keeps its latest
+// status until the job is finished, then it stores the job
+async fn collect(jobs: DispatchedJobs, mut client: ListenerClient<Channel>, spec: SendRequest, store: Option<Store>) {
+    let id = spec.id;
+    let res = async {
+        let mut request = Request::new(JobRequest{ id });
+        telemetry::inject(request.metadata_mut());
+        let mut updates = client.watch_job(request).await?.into_inner();
+        while let Some(status) = updates.message().await? {
+            jobs.update(id, status);
+        }
+        Ok::<(), Status>(())
+    }.await;
+    match res {
+        Ok(()) => {
+            info!("collected the results of job {}", id);
+            jobs.finish(id, None);
+            let job = jobs.get(id);
+            if let (Some(store), Some(job)) = (store, job) {
+                let status = job.status.unwrap_or_default();
+                if let Err(e) = store.record(&job.initiator, &spec, &status) {
+                    error!("failed to store the results of job {}: {}", id, e);
+                    metrics::inc(metrics::ERRORS, &[("kind", "store")]);
+                }
+            }
+        },
+        Err(status) => {
+            error!("lost track of job {}: {}", id, status);
+            metrics::inc(metrics::ERRORS, &[("kind", "collect")]);
+            jobs.finish(id, Some(status.message().to_string()));
+        },
+    }
+}
+
+#[tonic::async_trait]
+impl controller_server::Controller for Controller {
+    async fn register(
+        &self,
+        request: Request<RegisterRequest>,
+    ) -> Result<Response<RegisterReply>, Status> {
+        let _timer = metrics::grpc("/listener.Controller/Register");
+        let agent_id = self.agents.register(request.into_inner())?;
+        info!("agent {} registered", agent_id);
+        Ok(Response::new(RegisterReply{
+            agent_id,
+            heartbeat_interval_ms: HEARTBEAT_INTERVAL.as_millis() as u32,
+        }))
+    }
+    async fn heartbeat(
+        &self,
+        request: Request<HeartbeatRequest>,
+    ) -> Result<Response<HeartbeatReply>, Status> {
+        let _timer = metrics::grpc("/listener.Controller/Heartbeat");
+        let registered = self.agents.heartbeat(request.get_ref());
+        Ok(Response::new(HeartbeatReply{ registered }))
+    }
+    async fn list_agents(
+        &self,
+        _request: Request<ListAgentsRequest>,
+    ) -> Result<Response<AgentList>, Status> {
+        let _timer = metrics::grpc("/listener.Controller/ListAgents");
+        Ok(Response::new(AgentList{ agents: self.agents.list() }))
+    }
+    async fn dispatch(
+        &self,
+        request: Request<DispatchRequest>,
+    ) -> Result<Response<DispatchReply>, Status> {
+        let _timer = metrics::grpc("/listener.Controller/Dispatch");
+        let span = info_span!("dispatch", initiator = request.get_ref().initiator, server = request.get_ref().server);
+        telemetry::join(&span, request.metadata());
+        let DispatchRequest{ initiator, server, request } = request.into_inner();
+        let mut send = request.ok_or_else(|| Status::invalid_argument("missing request"))?;
+        let initiator_agent = self.agents.alive(&initiator)?;
+        let server_agent = self.agents.alive(&server)?;
+        send.address = format!("{}:{}", server_agent.address, server_agent.server_port);
+        let mut client = ListenerClient::connect(format!("http://{}:{}", initiator_agent.address, initiator_agent.initiator_port)).instrument(span.clone()).await.
+            map_err(|e| Status::unavailable(format!("failed to connect to agent {}: {}", initiator, e)))?;
+        send.id = self.reserve(&mut client, send.id, &initiator, &server).instrument(span.clone()).await?;
+        let id = send.id;
+        let spec = send.clone();
+        let mut send_request = Request::new(send);
+        telemetry::inject(send_request.metadata_mut());
+        let reply = match client.send(send_request).instrument(span.clone()).await {
+            Ok(reply) => reply.into_inner(),
+            Err(status) => {
+                self.jobs.release(id);
+                return Err(status);
+            },
+        };
+        info!(parent: &span, "dispatched job {} from {} to {}", id, initiator, server);
+        tokio::spawn(collect(self.jobs.clone(), client, spec, self.store.clone()).instrument(span));
+        Ok(Response::new(DispatchReply{
+            id,
+            reply: Some(reply),
+        }))
+    }
+    async fn get_job(
+        &self,
+        request: Request<JobRequest>,
+    ) -> Result<Response<DispatchedJob>, Status> {
+        let _timer = metrics::grpc("/listener.Controller/GetJob");
+        let id = request.get_ref().id;
+        match self.jobs.get(id) {
+            Some(job) => Ok(Response::new(job)),
+            None => Err(Status::not_found(format!("job {} not found", id))),
+        }
+    }
+    async fn list_jobs(
+        &self,
+        _request: Request<ListJobsRequest>,
+    ) -> Result<Response<DispatchedJobList>, Status> {
+        let _timer = metrics::grpc("/listener.Controller/ListJobs");
+        Ok(Response::new(DispatchedJobList{ jobs: self.jobs.list() }))
+    }
+    async fn query_results(
+        &self,
+        request: Request<QueryResultsRequest>,
+    ) -> Result<Response<QueryResultsReply>, Status> {
+        let _timer = metrics::grpc("/listener.Controller/QueryResults");
+        let store = self.store.as_ref().ok_or_else(|| Status::failed_precondition("the controller has no results database"))?;
+        match store.query(request.into_inner()).await {
+            Ok(results) => Ok(Response::new(QueryResultsReply{ results })),
+            Err(e) => Err(Status::internal(e.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picked_ids_count_up_and_wrap_around() {
+        let jobs = DispatchedJobs::default();
+        jobs.next.store(u32::MAX, Ordering::Relaxed);
+        assert_eq!(jobs.reserve(0, "a", "b").unwrap(), u32::MAX);
+        assert_eq!(jobs.reserve(0, "a", "b").unwrap(), CONTROLLER_JOB_IDS);
+        assert_eq!(jobs.reserve(0, "a", "b").unwrap(), CONTROLLER_JOB_IDS + 1);
+    }
+
+    #[test]
+    fn picked_ids_in_use_are_not_reused() {
+        let jobs = DispatchedJobs::default();
+        jobs.next.store(CONTROLLER_JOB_IDS, Ordering::Relaxed);
+        jobs.reserve(0, "a", "b").unwrap();
+        jobs.next.store(CONTROLLER_JOB_IDS, Ordering::Relaxed);
+        assert_eq!(jobs.reserve(0, "a", "b").unwrap_err().code(), Code::ResourceExhausted);
+    }
+
+    #[test]
+    fn explicit_ids_stay_below_the_controller_range() {
+        let jobs = DispatchedJobs::default();
+        assert_eq!(jobs.reserve(7, "a", "b").unwrap(), 7);
+        assert_eq!(jobs.reserve(7, "a", "b").unwrap_err().code(), Code::AlreadyExists);
+        assert_eq!(jobs.reserve(CONTROLLER_JOB_IDS, "a", "b").unwrap_err().code(), Code::InvalidArgument);
+    }
+
+    #[test]
+    fn finished_jobs_stay_listed() {
+        let jobs = DispatchedJobs::default();
+        let id = jobs.reserve(0, "a", "b").unwrap();
+        jobs.finish(id, Some("lost".to_string()));
+        jobs.reserve(0, "a", "b").unwrap();
+        assert_eq!(jobs.get(id).unwrap().error, "lost");
+    }
+}
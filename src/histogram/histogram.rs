@@ -0,0 +1,44 @@
+use std::time::Duration;
+use hdrhistogram::Histogram;
+use hdrhistogram::serialization::{Deserializer, Serializer, V2DeflateSerializer};
+
+// latencies are recorded in nanoseconds, the histogram grows past an hour
+// instead of rejecting values so histograms of any range can be merged
+const HIGHEST_TRACKABLE_NS: u64 = 3_600_000_000_000;
+const SIGNIFICANT_DIGITS: u8 = 3;
+
+// new returns an empty latency histogram
+pub fn new() -> Histogram<u64> {
+    let mut histogram = Histogram::new_with_bounds(1, HIGHEST_TRACKABLE_NS, SIGNIFICANT_DIGITS).unwrap();
+    histogram.auto(true);
+    histogram
+}
+
+pub fn record(histogram: &mut Histogram<u64>, latency: Duration) {
+    histogram.saturating_record(latency.as_nanos().max(1) as u64);
+}
+
+// encode serializes a histogram in the V2 deflate format of HdrHistogram
+pub fn encode(histogram: &Histogram<u64>) -> anyhow::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    V2DeflateSerializer::new().serialize(histogram, &mut buf)?;
+    Ok(buf)
+}
+
+pub fn decode(mut buf: &[u8]) -> anyhow::Result<Histogram<u64>> {
+    let decoded: Histogram<u64> = Deserializer::new().deserialize(&mut buf)?;
+    let mut histogram = new();
+    histogram.add(&decoded)?;
+    Ok(histogram)
+}
+
+// merge decodes and adds encoded histograms, empty ones are skipped
+pub fn merge<'a>(encoded: impl IntoIterator<Item = &'a [u8]>) -> anyhow::Result<Histogram<u64>> {
+    let mut histogram = new();
+    for buf in encoded {
+        if !buf.is_empty() {
+            histogram.add(&decode(buf)?)?;
+        }
+    }
+    Ok(histogram)
+}
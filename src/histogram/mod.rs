@@ -0,0 +1 @@
+pub mod histogram;
\ No newline at end of file
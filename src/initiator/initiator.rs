@@ -1,31 +1,91 @@
-use std::{alloc::Layout, io::Write};
-use async_rdma::{LocalMrWriteAccess, Rdma, RdmaBuilder, MTU};
-use log::{error, info};
-use tonic::{transport::Server, Request, Response, Status};
+use std::collections::HashSet;
+use std::future::Future;
+use std::hash::Hash;
+use std::io::Write;
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use futures::future::{self, try_join_all};
+use futures::Stream;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use hdrhistogram::Histogram;
+use async_rdma::{ConnectionType as RdmaConnectionType, LocalMr, LocalMrWriteAccess, Rdma, RdmaBuilder, MTU};
+use tracing::{error, info, info_span, instrument, Instrument};
+use tonic::{transport::{Channel, Server}, Request, Response, Status};
 use crate::initiator::listener::listener::listener_server::{Listener, ListenerServer};
 use crate::initiator::listener::listener::{
-    SendReply, SendRequest, Operation, Mtu
+    SendReply, SendRequest, Operation, Mtu, ImmMode, ConnectionType,
+    CapabilitiesRequest, CapabilitiesReply, Device,
+    JobRequest, JobStatus, JobState, JobList, ListJobsRequest, Measurement, Matrix, Cell, Latency, Delivery,
+    QueryResultsRequest, QueryResultsReply,
 };
 use crate::server::connection_manager::connection_manager::{
-    ConnectRequest,
+    ConnectRequest, JobSpec, QpEndpoint,
+    CapabilitiesRequest as ServerCapabilitiesRequest,
+    JobRequest as ServerJobRequest,
+    ImmMode as ServerImmMode,
+    Operation as ServerOperation,
+    ConnectionType as ServerConnectionType,
     connection_client::ConnectionClient
 };
+use crate::capabilities::capabilities;
+use crate::rdma::rdma::{DeviceConfig, QpConfig, QosConfig, SgeLayout};
+use crate::pool::pool::{self, DomainKey, Domains, MrPool, PoolConfig};
+use crate::jobs::jobs::{self, CancelToken, IntervalReporter, Jobs, Progress};
+use crate::histogram::histogram;
+use crate::metrics::metrics;
+use crate::telemetry::telemetry;
+use crate::store::store::Store;
+use crate::protocol::protocol;
+
+// how often WatchJob checks a job for changes
+const WATCH_PERIOD: Duration = Duration::from_millis(200);
+// how often the result of a job is polled from the server and how long
+// past the server's receive timeout
+const SERVER_RESULT_PERIOD: Duration = Duration::from_millis(20);
+const SERVER_RESULT_GRACE: Duration = Duration::from_secs(2);
+// how late a job scheduled to start at a given time may start
+const START_TOLERANCE: Duration = Duration::from_millis(100);
 
 #[derive(Debug, Default)]
 pub struct Initiator{
     address: String,
+    device: DeviceConfig,
+    domains: Domains,
+    jobs: Jobs,
+    store: Option<Store>,
 }
 
 impl Initiator {
-    pub fn new(address: String) -> Initiator {
+    // finished jobs are kept in store if there is one, as jobs of the
+    // address other hosts reach the initiator at
+    pub fn new(address: String, advertise_address: String, device: DeviceConfig, pool: PoolConfig, store: Option<Store>) -> Initiator {
+        let jobs = match &store {
+            Some(store) => Jobs::with_store(store.clone(), advertise_address),
+            None => Jobs::default(),
+        };
         Initiator{
             address,
+            device,
+            domains: Domains::new(pool),
+            jobs,
+            store,
         }
     }
+    pub fn jobs(&self) -> Jobs {
+        self.jobs.clone()
+    }
     pub async fn run(&mut self) -> anyhow::Result<()> {
         info!("starting initiator at {}", self.address);
         let addr = self.address.parse().unwrap();
-        let listener = Initiator::default();
+        let listener = Initiator{
+            address: self.address.clone(),
+            device: self.device.clone(),
+            domains: self.domains.clone(),
+            jobs: self.jobs.clone(),
+            store: self.store.clone(),
+        };
         Server::builder()
             .add_service(ListenerServer::new(listener))
             .serve(addr)
@@ -34,104 +94,1035 @@ impl Initiator {
     }
 }
 
-pub async fn initiate(request: SendRequest) -> anyhow::Result<()> {
-    let init_address = format!("http://{}",request.address.clone());
-    info!("connecting to server at {}", init_address);
-    let mtu: Mtu = Mtu::try_from(request.mtu).unwrap();
-    let (mtu, mtu_2) = match mtu {
+// JobConfig is the validated rdma configuration of one message size of a job
+struct JobConfig {
+    device: DeviceConfig,
+    qp: QpConfig,
+    qos: QosConfig,
+    sge: Option<SgeLayout>,
+    connection_type: ConnectionType,
+    mtu: u32,
+    rdma_mtu: MTU,
+    tx_depth: u32,
+}
+
+fn job_config(request: &SendRequest, device: &DeviceConfig) -> anyhow::Result<JobConfig> {
+    let mtu = Mtu::try_from(request.mtu).map_err(|_| anyhow::anyhow!("invalid mtu {}", request.mtu))?;
+    let (mtu, rdma_mtu) = match mtu {
         Mtu::Mtu512 => (512, MTU::MTU512),
         Mtu::Mtu1024 => (1024, MTU::MTU1024),
         Mtu::Mtu2048 => (2048, MTU::MTU2048),
         Mtu::Mtu4096 => (4096, MTU::MTU4096)
     };
-    let mut init_client = ConnectionClient::connect(init_address).await?;
-    let connect_request = tonic::Request::new(ConnectRequest{
-        id: request.id,
-        messages: request.messages,
-        message_size: request.message_size,
-        mtu
-    });
-    let response = init_client.init(connect_request).await?;
-    let port = response.get_ref().port;
-    let address = request.address.split(":").next().unwrap();
-    let server_address = format!("{}:{}", address, port);
-    info!("connecting to server at {}", server_address);
-    let op = Operation::try_from(request.op).unwrap();
+    let device = device.merge(DeviceConfig::new(request.device.clone(), request.ib_port, request.gid_index)?);
+    let mut qp = QpConfig{
+        max_send_wr: request.max_send_wr,
+        max_recv_wr: request.max_recv_wr,
+        max_sge: request.max_sge,
+        cq_size: request.cq_size,
+        max_inline_data: request.max_inline_data,
+        retry_count: request.retry_count,
+        rnr_retry: request.rnr_retry,
+        timeout: request.timeout,
+        min_rnr_timer: request.min_rnr_timer,
+    };
+    let sge = SgeLayout::new(request.message_size, request.sge_count, &request.sge_sizes)?;
+    let connection_type = ConnectionType::try_from(request.connection_type).unwrap_or(ConnectionType::RcTcp);
+    if connection_type == ConnectionType::RcGrpc {
+        // queue pairs connected over grpc have no async-rdma agent, they can
+        // only post plain sends and receives of a known size
+        if request.op != Operation::Send as i32 || sge.is_some() {
+            return Err(anyhow::anyhow!("RC_GRPC connections only support SEND without scatter-gather layouts"));
+        }
+        // the server posts its receives after the handshake, retry until it has
+        qp.rnr_retry.get_or_insert(7);
+    }
+    if let Some(sge) = &sge {
+        if request.op != Operation::Send as i32 {
+            return Err(anyhow::anyhow!("scatter-gather layouts are only supported for SEND"));
+        }
+        match qp.max_sge {
+            Some(max_sge) if max_sge < sge.count() => {
+                return Err(anyhow::anyhow!("max sge {} is smaller than the {} sge layout elements", max_sge, sge.count()));
+            },
+            Some(_) => (),
+            None => qp.max_sge = Some(sge.count()),
+        }
+    }
+    qp.validate()?;
+    let tx_depth = request.tx_depth.max(1);
+    if let Some(max_send_wr) = qp.max_send_wr {
+        if tx_depth > max_send_wr {
+            return Err(anyhow::anyhow!("tx depth {} is larger than max send wr {}", tx_depth, max_send_wr));
+        }
+    }
+    let qos = QosConfig::new(request.traffic_class, request.dscp, request.service_level, request.flow_label)?;
+    qos.supported()?;
+    Ok(JobConfig{
+        device,
+        qp,
+        qos,
+        sge,
+        connection_type,
+        mtu,
+        rdma_mtu,
+        tx_depth,
+    })
+}
+
+// sweep_sizes returns the message sizes a job runs: the explicit sweep list,
+// the doubling range from sweep_min (2 if unset) to sweep_max, or message_size
+fn sweep_sizes(request: &SendRequest) -> anyhow::Result<Vec<u32>> {
+    if !request.sweep_sizes.is_empty() {
+        if request.sweep_sizes.contains(&0) {
+            return Err(anyhow::anyhow!("sweep sizes must be greater than 0"));
+        }
+        return Ok(distinct(&request.sweep_sizes));
+    }
+    if request.sweep_max == 0 {
+        return Ok(vec![request.message_size]);
+    }
+    let min = if request.sweep_min == 0 { 2 } else { request.sweep_min };
+    if min > request.sweep_max {
+        return Err(anyhow::anyhow!("sweep min {} is larger than sweep max {}", min, request.sweep_max));
+    }
+    let mut sizes = Vec::new();
+    let mut size = min;
+    while size <= request.sweep_max {
+        sizes.push(size);
+        size = match size.checked_mul(2) {
+            Some(size) => size,
+            None => break,
+        };
+    }
+    Ok(sizes)
+}
+
+// distinct keeps the first of values that are given more than once
+fn distinct<T: Copy + Eq + Hash>(values: &[T]) -> Vec<T> {
+    let mut seen = HashSet::new();
+    values.iter().copied().filter(|value| seen.insert(*value)).collect()
+}
+
+// connection_messages returns the messages a connection carries for the
+// warmup and all trials
+fn connection_messages(messages: u32, warmup: u32, repetitions: u32) -> anyhow::Result<u32> {
+    messages.checked_mul(repetitions).and_then(|m| m.checked_add(warmup)).
+        ok_or_else(|| anyhow::anyhow!("{} repetitions of {} messages overflow the message count", repetitions, messages))
+}
+
+// initiate connects the first message size of the job before returning so
+// setup errors reach the caller, the transfers run in the background
+#[instrument(skip_all, fields(job_id = request.id))]
+pub async fn initiate(request: SendRequest, device: DeviceConfig, domains: Domains, jobs: Jobs) -> anyhow::Result<QosConfig> {
+    if let Some(matrix) = request.matrix.clone() {
+        return initiate_matrix(request, matrix, device, domains, jobs);
+    }
+    let warmup = request.warmup_messages;
+    let repetitions = request.repetitions.max(1);
+    let messages = connection_messages(request.messages, warmup, repetitions)?;
+    let mut sub_tests = Vec::new();
+    for size in sweep_sizes(&request)? {
+        let request = SendRequest{
+            message_size: size,
+            ..request.clone()
+        };
+        let config = job_config(&request, &device)?;
+        sub_tests.push((request, config));
+    }
+    let (first, first_config) = &sub_tests[0];
+    let qos = first_config.qos.clone();
+    let id = request.id;
+    // the job is registered before connecting so a second request with the
+    // same id is refused instead of connecting to the server as well
+    let token = jobs.start(&request)?;
+    let conn = match connect(first, first_config, messages, &domains).await {
+        Ok(conn) => conn,
+        Err(e) => {
+            jobs.fail(id, e.to_string());
+            return Err(e);
+        },
+    };
+    let sent = messages as u64 * sub_tests.len() as u64;
     tokio::task::spawn(async move{
-        let b = RdmaBuilder::default().
-        set_max_message_length(request.message_size as usize).
-        set_mtu(mtu_2).
-        connect(server_address.clone());
-        //b.set_conn_type(ConnectionType::RCCM);
+        if let Err(e) = wait_for_start(id, request.start_at_unix_ns, &token).await {
+            error!("start error: {}", e);
+            abandon(&request, &jobs, None, e.to_string()).await;
+            return;
+        }
+        jobs.started(id);
+        let progress = Arc::new(Progress::default());
+        let reporter = interval_reporter(&request, &jobs, &progress);
+        let mut first = Some(conn);
+        let mut measurements = Vec::new();
+        for (request, config) in sub_tests {
+            let conn = match first.take() {
+                Some((rdma, pool)) => JobConnection::new(rdma, pool, &token),
+                None => match connect(&request, &config, messages, &domains).await {
+                    Ok((rdma, pool)) => JobConnection::new(rdma, pool, &token),
+                    Err(e) => {
+                        error!("rdma connect error: {}", e);
+                        abandon(&request, &jobs, reporter, e.to_string()).await;
+                        return;
+                    }
+                },
+            };
+            match run_trials(std::slice::from_ref(&conn), &request, &config, warmup, repetitions, &progress).await {
+                Ok(trials) => {
+                    let measurement = jobs::total(request.message_size, &trials);
+                    info!("sent {} bytes in {} ms with {:?}", measurement.bytes, measurement.elapsed_ns / 1_000_000, config.qos);
+                    let cell = jobs::cell(request.mtu, request.message_size, config.tx_depth, 1, trials);
+                    if repetitions > 1 {
+                        log_trials(&cell);
+                    }
+                    if let Some(latency) = &measurement.latency {
+                        jobs.add_latency(id, latency);
+                    }
+                    measurements.push(measurement.clone());
+                    jobs.add_measurement(id, measurement);
+                    jobs.add_cell(id, cell);
+                },
+                Err(e) => {
+                    error!("operation error: {}", e);
+                    abandon(&request, &jobs, reporter, e.to_string()).await;
+                    return;
+                },
+            }
+        }
+        finish(reporter).await;
+        jobs.sent(id);
+        if let Some((receive_wait, delivery)) = server_result(&request, sent).await {
+            jobs.set_server_result(id, receive_wait, delivery);
+        }
+        if measurements.len() > 1 {
+            info!("job {} sweep results:", id);
+            info!("{:>12} {:>10} {:>12} {:>12}", "size", "messages", "Gb/s", "us/msg");
+            for m in &measurements {
+                info!("{:>12} {:>10} {:>12.3} {:>12.3}", m.message_size, m.messages, m.bandwidth_gbps, m.time_per_message_us);
+            }
+        }
+        jobs.complete(id);
+    }.instrument(info_span!("data_path", job_id = id)));
 
-        let rdma = match b.await{
-            Ok(rdma) => rdma,
-            Err(e) => {
-                error!("rdma connect error: {}", e);
-                return;
+    Ok(qos)
+}
+
+// matrix_cells expands the matrix of a request into one request per cell
+// and the number of connections the cell runs in parallel
+fn matrix_cells(request: &SendRequest, matrix: &Matrix) -> anyhow::Result<Vec<(SendRequest, u32)>> {
+    if !request.sweep_sizes.is_empty() || request.sweep_max != 0 {
+        return Err(anyhow::anyhow!("sweeps and matrices are mutually exclusive"));
+    }
+    let or_default = |values: &[u32], default: u32| if values.is_empty() { vec![default] } else { distinct(values) };
+    let mtus = if matrix.mtus.is_empty() { vec![request.mtu] } else { distinct(&matrix.mtus) };
+    let sizes = or_default(&matrix.message_sizes, request.message_size);
+    let depths = or_default(&matrix.tx_depths, request.tx_depth.max(1));
+    let connections = or_default(&matrix.connections, 1);
+    if sizes.contains(&0) || depths.contains(&0) || connections.contains(&0) {
+        return Err(anyhow::anyhow!("matrix sizes, tx depths and connections must be greater than 0"));
+    }
+    let mut cells = Vec::new();
+    for mtu in &mtus {
+        for size in &sizes {
+            for depth in &depths {
+                for connections in &connections {
+                    let request = SendRequest{
+                        mtu: *mtu,
+                        message_size: *size,
+                        tx_depth: *depth,
+                        matrix: None,
+                        ..request.clone()
+                    };
+                    cells.push((request, *connections));
+                }
             }
+        }
+    }
+    Ok(cells)
+}
+
+// initiate_matrix validates every cell of a matrix job before returning,
+// the cells run one after another in the background
+fn initiate_matrix(request: SendRequest, matrix: Matrix, device: DeviceConfig, domains: Domains, jobs: Jobs) -> anyhow::Result<QosConfig> {
+    let warmup = request.warmup_messages;
+    let repetitions = request.repetitions.max(1);
+    let messages = connection_messages(request.messages, warmup, repetitions)?;
+    let mut cells = Vec::new();
+    for (request, connections) in matrix_cells(&request, &matrix)? {
+        let config = job_config(&request, &device)?;
+        cells.push((request, config, connections));
+    }
+    let qos = cells[0].1.qos.clone();
+    let sent = cells.iter().map(|(_, _, connections)| messages as u64 * *connections as u64).sum();
+    let id = request.id;
+    let token = jobs.start(&request)?;
+    info!("job {} runs a matrix of {} cells", id, cells.len());
+    tokio::task::spawn(async move{
+        if let Err(e) = wait_for_start(id, request.start_at_unix_ns, &token).await {
+            error!("start error: {}", e);
+            abandon(&request, &jobs, None, e.to_string()).await;
+            return;
+        }
+        jobs.started(id);
+        let progress = Arc::new(Progress::default());
+        let reporter = interval_reporter(&request, &jobs, &progress);
+        let mut results = Vec::new();
+        for (request, config, connections) in cells {
+            match run_cell(&request, &config, connections, messages, warmup, repetitions, &domains, &token, &progress).await {
+                Ok(cell) => {
+                    if let Some(latency) = &cell.latency {
+                        jobs.add_latency(id, latency);
+                    }
+                    results.push(cell.clone());
+                    jobs.add_cell(id, cell);
+                },
+                Err(e) => {
+                    error!("matrix cell error: {}", e);
+                    abandon(&request, &jobs, reporter, e.to_string()).await;
+                    return;
+                },
+            }
+        }
+        finish(reporter).await;
+        jobs.sent(id);
+        if let Some((receive_wait, delivery)) = server_result(&request, sent).await {
+            jobs.set_server_result(id, receive_wait, delivery);
+        }
+        info!("job {} matrix results:", id);
+        info!("{:>8} {:>12} {:>8} {:>6} {:>12} {:>10} {:>12} {:>10}", "mtu", "size", "depth", "qps", "Gb/s", "stddev", "us/msg", "stddev");
+        for c in &results {
+            let mtu = Mtu::try_from(c.mtu).map(|mtu| mtu.as_str_name()).unwrap_or("UNKNOWN");
+            let bandwidth = c.bandwidth_gbps.clone().unwrap_or_default();
+            let time_per_message = c.time_per_message_us.clone().unwrap_or_default();
+            info!("{:>8} {:>12} {:>8} {:>6} {:>12.3} {:>10.3} {:>12.3} {:>10.3}", mtu, c.message_size, c.tx_depth, c.connections, bandwidth.mean, bandwidth.stddev, time_per_message.mean, time_per_message.stddev);
+        }
+        jobs.complete(id);
+    }.instrument(info_span!("data_path", job_id = id)));
+    Ok(qos)
+}
+
+// run_cell opens the connections of a cell and runs its trials on all of them
+#[allow(clippy::too_many_arguments)]
+async fn run_cell(request: &SendRequest, config: &JobConfig, connections: u32, messages: u32, warmup: u32, repetitions: u32, domains: &Domains, token: &CancelToken, progress: &Progress) -> anyhow::Result<Cell> {
+    let mut conns = Vec::with_capacity(connections as usize);
+    for _ in 0..connections {
+        let (rdma, pool) = connect(request, config, messages, domains).await?;
+        conns.push(JobConnection::new(rdma, pool, token));
+    }
+    let trials = run_trials(&conns, request, config, warmup, repetitions, progress).await?;
+    Ok(jobs::cell(request.mtu, request.message_size, config.tx_depth, connections, trials))
+}
+
+// run_trials sends the warmup messages, which are not measured, and then
+// measures every repetition on all connections in parallel. Every connection
+// carries the warmup and all trials as one job on the server, the warmup
+// registers the regions the trials reuse
+async fn run_trials(conns: &[JobConnection], request: &SendRequest, config: &JobConfig, warmup: u32, repetitions: u32, progress: &Progress) -> anyhow::Result<Vec<Measurement>> {
+    if warmup > 0 {
+        let warmup_request = SendRequest{
+            messages: warmup,
+            ..request.clone()
         };
+        try_join_all(conns.iter().map(|conn| transfer(conn, &warmup_request, config, 0, None))).await?;
+    }
+    let mut trials = Vec::with_capacity(repetitions as usize);
+    for trial in 0..repetitions {
+        let first_seq = warmup + trial * request.messages;
         let start = tokio::time::Instant::now();
-        let res = match op{
-            Operation::Send => {
-                info!("send operation");
-                send(&rdma, request.message_size, request.messages).await
+        let results = try_join_all(conns.iter().map(|conn| transfer(conn, request, config, first_seq, Some(progress)))).await?;
+        trials.push(Measurement{
+            latency: Some(jobs::merge_latencies(results.iter().filter_map(|m| m.latency.as_ref()))),
+            ..jobs::measurement(request.message_size, request.messages * conns.len() as u32, start.elapsed())
+        });
+    }
+    Ok(trials)
+}
+
+// wait_for_start sleeps until the start time of a job. A job which missed
+// it by more than START_TOLERANCE fails, it would not overlap with the jobs
+// it was scheduled with
+async fn wait_for_start(id: u32, start_at_unix_ns: u64, token: &CancelToken) -> anyhow::Result<()> {
+    if start_at_unix_ns > 0 {
+        let start = UNIX_EPOCH + Duration::from_nanos(start_at_unix_ns);
+        match start.duration_since(SystemTime::now()) {
+            Ok(delay) => {
+                info!("job {} starts in {} ms", id, delay.as_millis());
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => (),
+                    _ = token.cancelled() => (),
+                }
             },
-            Operation::SendWithImm => {
-                info!("send_with_imm operation");
-                send_with_imm(&rdma).await
+            Err(e) if e.duration() > START_TOLERANCE => {
+                return Err(anyhow::anyhow!("missed its start time by {} ms", e.duration().as_millis()));
+            },
+            Err(e) => info!("job {} starts {} ms late", id, e.duration().as_millis()),
+        }
+    }
+    if token.is_cancelled() {
+        return Err(anyhow::anyhow!("cancelled"));
+    }
+    Ok(())
+}
+
+// interval_reporter starts the interval reports of a job if it asks for them
+fn interval_reporter(request: &SendRequest, jobs: &Jobs, progress: &Arc<Progress>) -> Option<IntervalReporter> {
+    if request.interval_ms == 0 {
+        return None;
+    }
+    Some(IntervalReporter::start(jobs.clone(), request.id, progress.clone(), Duration::from_millis(request.interval_ms as u64)))
+}
+
+async fn finish(reporter: Option<IntervalReporter>) {
+    if let Some(reporter) = reporter {
+        reporter.finish().await;
+    }
+}
+
+// abandon ends the data path of a job which failed or was cancelled, the
+// server stops waiting for the rest of its messages
+async fn abandon(request: &SendRequest, jobs: &Jobs, reporter: Option<IntervalReporter>, error: String) {
+    finish(reporter).await;
+    cancel_server(request).await;
+    jobs.fail(request.id, error);
+}
+
+// cancel_server cancels the job on the server, servers predating CancelJob
+// give up on its connections after their receive timeout
+async fn cancel_server(request: &SendRequest) {
+    let mut client = match ConnectionClient::connect(format!("http://{}", request.address)).await {
+        Ok(client) => client,
+        Err(e) => {
+            error!("failed to cancel job {} on {}: {}", request.id, request.address, e);
+            return;
+        },
+    };
+    let mut job_request = tonic::Request::new(ServerJobRequest{ id: request.id });
+    telemetry::inject(job_request.metadata_mut());
+    match client.cancel_job(job_request).await {
+        Ok(_) => info!("job {} cancelled on {}", request.id, request.address),
+        Err(status) if matches!(status.code(), tonic::Code::Unimplemented | tonic::Code::NotFound) => (),
+        Err(status) => error!("failed to cancel job {} on {}: {}", request.id, request.address, status),
+    }
+}
+
+// server_result fetches the receive wait times and counters of the job from
+// the server and compares them with the sent messages, servers predating
+// GetJob have none. The receives of the last messages may complete after the
+// sends, so the result is polled until all connections of the job are
+// finished. A connection missing messages finishes after the receive timeout
+async fn server_result(request: &SendRequest, sent: u64) -> Option<(Latency, Delivery)> {
+    let mut client = ConnectionClient::connect(format!("http://{}", request.address)).await.ok()?;
+    let receive_timeout = match request.receive_timeout_ms {
+        0 => protocol::DEFAULT_RECEIVE_TIMEOUT,
+        ms => Duration::from_millis(ms as u64),
+    };
+    let deadline = tokio::time::Instant::now() + receive_timeout + SERVER_RESULT_GRACE;
+    while tokio::time::Instant::now() < deadline {
+        let mut job_request = tonic::Request::new(ServerJobRequest{ id: request.id });
+        telemetry::inject(job_request.metadata_mut());
+        let result = match client.get_job(job_request).await {
+            Ok(response) => response.into_inner(),
+            Err(status) => {
+                if status.code() != tonic::Code::Unimplemented {
+                    error!("failed to get server result of job {}: {}", request.id, status);
+                }
+                return None;
             },
         };
-        match res {
-            Ok(_) => info!("sent {} bytes in {} ms ",request.message_size * request.messages, start.elapsed().as_millis()),
-            Err(e) => error!("operation error: {}", e),
+        if result.active_connections == 0 {
+            if result.received < sent || result.receive_errors > 0 || result.integrity_errors > 0 {
+                error!("job {} sent {} messages, the server received {} with {} receive and {} integrity errors",
+                    request.id, sent, result.received, result.receive_errors, result.integrity_errors);
+            }
+            let receive_wait = jobs::merge_latencies(Some(&Latency{
+                histogram: result.receive_wait_histogram,
+                ..Default::default()
+            }));
+            let delivery = Delivery{
+                sent,
+                received: result.received,
+                receive_errors: result.receive_errors,
+                integrity_errors: result.integrity_errors,
+            };
+            return Some((receive_wait, delivery));
         }
+        tokio::time::sleep(SERVER_RESULT_PERIOD).await;
+    }
+    error!("connections of job {} on {} did not finish", request.id, request.address);
+    None
+}
+
+// log_trials logs the trials of a cell and their statistics
+fn log_trials(cell: &Cell) {
+    info!("{} byte trials:", cell.message_size);
+    info!("{:>6} {:>12} {:>12}", "trial", "Gb/s", "us/msg");
+    for (i, m) in cell.trials.iter().enumerate() {
+        info!("{:>6} {:>12.3} {:>12.3}", i, m.bandwidth_gbps, m.time_per_message_us);
+    }
+    for (name, stats) in [("Gb/s", &cell.bandwidth_gbps), ("us/msg", &cell.time_per_message_us)] {
+        let stats = stats.clone().unwrap_or_default();
+        info!("{}: mean {:.3} stddev {:.3} min {:.3} max {:.3} ci95 +/-{:.3}", name, stats.mean, stats.stddev, stats.min, stats.max, stats.ci95);
+    }
+}
+
+// connect runs Init against the server and establishes the rdma connection
+// for messages messages, along with the pool its regions are taken from
+#[instrument(skip_all, fields(job_id = request.id, message_size = request.message_size))]
+async fn connect(request: &SendRequest, config: &JobConfig, messages: u32, domains: &Domains) -> anyhow::Result<(Rdma, Arc<MrPool>)> {
+    let res = establish(request, config, messages, domains).await;
+    if res.is_err() {
+        metrics::inc(metrics::ERRORS, &[("kind", "connect")]);
+    }
+    res
+}
+
+async fn establish(request: &SendRequest, config: &JobConfig, messages: u32, domains: &Domains) -> anyhow::Result<(Rdma, Arc<MrPool>)> {
+    let init_address = format!("http://{}",request.address.clone());
+    info!("connecting to server at {}", init_address);
+    let mut init_client = ConnectionClient::connect(init_address).await?;
+    check_capabilities(&mut init_client, request, config).await?;
+    let spec = JobSpec{
+        version: protocol::JOB_SPEC_VERSION,
+        id: request.id,
+        op: server_op(request),
+        messages,
+        message_size: request.message_size,
+        mtu: config.mtu,
+        imm_mode: request.imm_mode,
+        imm_data: request.imm_data,
+        device: request.server_device.clone(),
+        ib_port: request.server_ib_port,
+        gid_index: request.server_gid_index,
+        max_send_wr: request.max_send_wr,
+        max_recv_wr: request.max_recv_wr,
+        // the receive side takes every message into a single buffer
+        max_sge: request.max_sge,
+        cq_size: request.cq_size,
+        max_inline_data: request.max_inline_data,
+        retry_count: request.retry_count,
+        rnr_retry: config.qp.rnr_retry,
+        timeout: request.timeout,
+        min_rnr_timer: request.min_rnr_timer,
+        traffic_class: config.qos.traffic_class,
+        service_level: config.qos.service_level,
+        flow_label: config.qos.flow_label,
+        connection_type: ServerConnectionType::from(config.connection_type).into(),
+        tx_depth: config.tx_depth,
+        receive_timeout_ms: request.receive_timeout_ms,
+        skip_integrity_check: request.skip_integrity_check,
+        start_at_unix_ns: request.start_at_unix_ns,
+        warmup_messages: request.warmup_messages,
+    };
+    let builder = config.qp.apply(config.device.apply(RdmaBuilder::default())).
+        set_max_message_length(request.message_size as usize).
+        set_mtu(config.rdma_mtu);
+    // RC_GRPC creates the queue pair up front so its endpoint can travel in
+    // Init. The handshake only connects the queue pair, without the agent
+    // the other connection types start, so it is used with send_raw
+    let mut local_rdma = None;
+    let mut endpoint = None;
+    if config.connection_type == ConnectionType::RcGrpc {
+        let rdma = builder.build()?;
+        endpoint = Some(QpEndpoint::try_from(rdma.get_qp_endpoint())?);
+        local_rdma = Some(rdma);
+    }
+    // the flat fields are filled for servers predating JobSpec
+    #[allow(deprecated)]
+    let mut connect_request = tonic::Request::new(ConnectRequest{
+        id: request.id,
+        messages,
+        message_size: request.message_size,
+        mtu: config.mtu,
+        imm_mode: request.imm_mode,
+        imm_data: request.imm_data,
+        op: server_op(request),
+        spec: Some(spec),
+        endpoint,
     });
+    telemetry::inject(connect_request.metadata_mut());
+    let response = init_client.init(connect_request).await?.into_inner();
+    let port = response.port;
+    let address = request.address.split(":").next().unwrap().to_string();
+    let server_address = format!("{}:{}", address, port);
+    if let Some(mut rdma) = local_rdma {
+        let remote = response.endpoint.ok_or_else(|| anyhow::anyhow!("server at {} returned no qp endpoint", request.address))?;
+        rdma.qp_handshake(remote.try_into()?)?;
+        info!("qp handshake with {} done", request.address);
+        return Ok((rdma, Arc::new(domains.pool())));
+    }
+    info!("connecting to server at {}", server_address);
+    match config.connection_type {
+        ConnectionType::RcCm => {
+            let rdma = builder.set_conn_type(RdmaConnectionType::RCCM).
+                cm_connect(&address, &port.to_string()).await?;
+            Ok((rdma, Arc::new(domains.pool())))
+        },
+        // RC_TCP connections are made from the parent of their domain, so
+        // they share its protection domain and pool with earlier jobs
+        _ => {
+            let key = DomainKey{
+                device: config.device.clone(),
+                qp: config.qp.clone(),
+                mtu: config.mtu,
+                max_message_length: request.message_size as usize,
+            };
+            let domain = domains.get(key, builder)?;
+            let rdma = domain.rdma.new_connect(server_address.clone()).await?;
+            Ok((rdma, domain.pool.clone()))
+        },
+    }
+}
+
+// JobConnection is a connection of a job with the pool of the regions
+// registered against its protection domain
+struct JobConnection {
+    rdma: Rdma,
+    pool: Arc<MrPool>,
+    token: CancelToken,
+}
+
+impl JobConnection {
+    fn new(rdma: Rdma, pool: Arc<MrPool>, token: &CancelToken) -> JobConnection {
+        JobConnection{
+            rdma,
+            pool,
+            token: token.clone(),
+        }
+    }
+}
+
+// transfer runs the operation of the request over rdma and measures it,
+// first_seq is the position of its first message within the connection.
+// Messages are counted in progress if it is set. The buffers are taken
+// from the connection's pool before the measurement starts
+#[instrument(skip_all, fields(job_id = request.id, first_seq = first_seq))]
+async fn transfer(conn: &JobConnection, request: &SendRequest, config: &JobConfig, first_seq: u32, progress: Option<&Progress>) -> anyhow::Result<Measurement> {
+    let depth = config.tx_depth as usize;
+    let seqs = first_seq..first_seq + request.messages;
+    let op = Operation::try_from(request.op).unwrap_or(Operation::Send);
+    let imm_mode = ServerImmMode::from(ImmMode::try_from(request.imm_mode).unwrap_or(ImmMode::ImmConstant));
+    let (rdma, pool) = (&conn.rdma, &conn.pool);
+    let lmr = match &config.sge {
+        Some(_) => sge_buffer(rdma, pool, request.message_size, request.sge_alignment)?,
+        None => message_buffer(rdma, pool, request.message_size)?,
+    };
+    let recorder = Recorder{
+        latencies: Mutex::new(histogram::new()),
+        progress,
+        token: &conn.token,
+        message_size: request.message_size as u64,
+        pace: (request.message_rate > 0).then(|| Duration::from_secs_f64(1.0 / request.message_rate as f64)),
+    };
+    let start = tokio::time::Instant::now();
+    let res = match op{
+        Operation::Send => match &config.sge {
+            Some(sge) => {
+                info!("send operation with {} sge in one region", sge.count());
+                send(rdma, &lmr, request.message_size, seqs, depth, &recorder).await
+            },
+            None if config.connection_type == ConnectionType::RcGrpc => {
+                info!("raw send operation");
+                send_raw(rdma, &lmr, request.message_size, seqs, depth, &recorder).await
+            },
+            None => {
+                info!("send operation");
+                send(rdma, &lmr, request.message_size, seqs, depth, &recorder).await
+            },
+        },
+        Operation::SendWithImm => {
+            info!("send_with_imm operation");
+            send_with_imm(rdma, &lmr, request.message_size, seqs, depth, &recorder, imm_mode, request.imm_data, request.id).await
+        },
+        Operation::SendMixed => {
+            info!("send_mixed operation");
+            send_mixed(rdma, &lmr, request.message_size, seqs, depth, &recorder, imm_mode, request.imm_data, request.id).await
+        },
+    };
+    let elapsed = start.elapsed();
+    let stats = pool::stats();
+    info!("mr pool: {} registrations, {} reuses, {} evictions", stats.registrations, stats.reuses, stats.evictions);
+    res?;
+    pool.put(lmr);
+    let latency = jobs::latency(&recorder.latencies.lock().unwrap());
+    Ok(Measurement{
+        latency: Some(latency),
+        ..jobs::measurement(request.message_size, request.messages, elapsed)
+    })
+}
 
+// check_capabilities fails when the server can't run the requested operation.
+// Servers predating GetCapabilities are assumed to support SEND and SEND_WITH_IMM
+// over RC_TCP, which every server accepts
+async fn check_capabilities(client: &mut ConnectionClient<Channel>, request: &SendRequest, config: &JobConfig) -> anyhow::Result<()> {
+    let mut capabilities_request = tonic::Request::new(ServerCapabilitiesRequest{});
+    telemetry::inject(capabilities_request.metadata_mut());
+    let (version, operations, transports, mtus, max_message_size) = match client.get_capabilities(capabilities_request).await {
+        Ok(response) => {
+            let response = response.into_inner();
+            (response.version, response.operations, response.transports, response.mtus, response.max_message_size)
+        },
+        Err(status) if status.code() == tonic::Code::Unimplemented => {
+            let operations = vec![ServerOperation::Send.into(), ServerOperation::SendWithImm.into()];
+            ("unknown".to_string(), operations, Vec::new(), capabilities::MTUS.to_vec(), capabilities::MAX_MESSAGE_SIZE)
+        },
+        Err(status) => return Err(status.into()),
+    };
+    if config.connection_type != ConnectionType::RcTcp {
+        let connection_type = config.connection_type.as_str_name();
+        if !transports.iter().any(|t| t == connection_type) {
+            return Err(anyhow::anyhow!("server at {} (rocky-rs {}) does not support connection type {}", request.address, version, connection_type));
+        }
+    }
+    if !operations.contains(&server_op(request)) {
+        let op = Operation::try_from(request.op).map(|op| op.as_str_name()).unwrap_or("UNKNOWN");
+        return Err(anyhow::anyhow!("server at {} (rocky-rs {}) does not support operation {}", request.address, version, op));
+    }
+    if !mtus.contains(&config.mtu) {
+        return Err(anyhow::anyhow!("server at {} (rocky-rs {}) does not support mtu {}, supported {:?}", request.address, version, config.mtu, mtus));
+    }
+    if request.message_size > max_message_size {
+        return Err(anyhow::anyhow!("server at {} (rocky-rs {}) supports messages up to {} bytes, requested {}", request.address, version, max_message_size, request.message_size));
+    }
     Ok(())
 }
 
-pub async fn send(rdma: &Rdma, message_size: u32, messages: u32) -> anyhow::Result<()> {
-    let layout = Layout::from_size_align(message_size as usize, 1).unwrap();
-    let mut lmr = rdma.alloc_local_mr(layout)?;
+// server_op is the operation of a job as the server's enum value
+fn server_op(request: &SendRequest) -> i32 {
+    ServerOperation::from(Operation::try_from(request.op).unwrap_or(Operation::Send)).into()
+}
+
+// message_buffer takes a region for message_size bytes from the pool and fills it
+fn message_buffer(rdma: &Rdma, pool: &MrPool, message_size: u32) -> anyhow::Result<LocalMr> {
+    let mut lmr = pool.take(rdma, message_size as usize)?;
     let buf = vec![1_u8; message_size as usize];
     let _num = lmr.as_mut_slice().write(buf.as_slice())?;
-    for _ in 0..messages{
-        rdma.send(&lmr).await?;
+    Ok(lmr)
+}
+
+// Recorder records the completion latencies of a transfer and counts its
+// messages for the interval reports of the job
+pub struct Recorder<'a> {
+    latencies: Mutex<Histogram<u64>>,
+    progress: Option<&'a Progress>,
+    token: &'a CancelToken,
+    message_size: u64,
+    // time between the posts of consecutive messages of a rate limited job
+    pace: Option<Duration>,
+}
+
+impl Recorder<'_> {
+    fn record(&self, res: &anyhow::Result<()>, latency: Duration) {
+        match res {
+            Ok(()) => {
+                metrics::sent(self.message_size);
+                histogram::record(&mut self.latencies.lock().unwrap(), latency);
+                if let Some(progress) = self.progress {
+                    progress.message(self.message_size);
+                }
+            },
+            Err(_) => {
+                metrics::inc(metrics::ERRORS, &[("kind", "send")]);
+                if let Some(progress) = self.progress {
+                    progress.error();
+                }
+            },
+        }
     }
-    Ok(())
 }
 
-pub async fn send_with_imm(rdma: &Rdma) -> anyhow::Result<()> {
-    let mut lmr = rdma.alloc_local_mr(Layout::new::<[u8; 8]>())?;
-    {
-        let mut mr_cursor = lmr.as_mut_slice_cursor();
-        let _num = mr_cursor.write(&[1_u8; 4])?;
-        let _num = mr_cursor.write(&[1_u8; 4])?;
+// post runs f for the sequence number of every message with up to
+// tx_depth of them in flight and records the completion of each. Rate
+// limited jobs post every message at its slot relative to the first one.
+// A cancelled job posts no more messages and fails once the ones in flight
+// completed, their regions must stay registered until then
+async fn post<F, Fut>(seqs: Range<u32>, tx_depth: usize, recorder: &Recorder<'_>, mut f: F) -> anyhow::Result<()>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = anyhow::Result<()>>,
+{
+    let first = seqs.start;
+    let start = tokio::time::Instant::now();
+    let pace = recorder.pace;
+    // the stream holds its own tokens, borrowed ones don't keep the data
+    // path task Send
+    let token = recorder.token.clone();
+    let pacing = token.clone();
+    let seqs = stream::iter(seqs).then(move |seq| {
+        let token = pacing.clone();
+        async move {
+            if let Some(pace) = pace {
+                tokio::select! {
+                    _ = tokio::time::sleep_until(start + pace * (seq - first)) => (),
+                    _ = token.cancelled() => (),
+                }
+            }
+            seq
+        }
+    }).take_while(move |_| future::ready(!token.is_cancelled())).map(Ok);
+    seqs.try_for_each_concurrent(tx_depth, |seq| {
+        let start = std::time::Instant::now();
+        let op = f(seq);
+        async move {
+            let res = op.await;
+            recorder.record(&res, start.elapsed());
+            res
+        }
+    }).await?;
+    if recorder.token.is_cancelled() {
+        return Err(anyhow::anyhow!("cancelled"));
     }
-    rdma.send_with_imm(&lmr, 1_u32).await?;
     Ok(())
 }
 
+pub async fn send(rdma: &Rdma, lmr: &LocalMr, message_size: u32, seqs: Range<u32>, tx_depth: usize, recorder: &Recorder<'_>) -> anyhow::Result<()> {
+    let message = &lmr.get(0..message_size as usize)?;
+    post(seqs, tx_depth, recorder, |_| async move {
+        rdma.send(message).await?;
+        Ok(())
+    }).await
+}
+
+// send_raw sends over a queue pair without an agent, see establish
+pub async fn send_raw(rdma: &Rdma, lmr: &LocalMr, message_size: u32, seqs: Range<u32>, tx_depth: usize, recorder: &Recorder<'_>) -> anyhow::Result<()> {
+    let message = &lmr.get(0..message_size as usize)?;
+    post(seqs, tx_depth, recorder, |_| async move {
+        rdma.send_raw(message).await?;
+        Ok(())
+    }).await
+}
+
+// sge_buffer takes a filled region for the elements of a layout, they lie
+// back to back in it. async-rdma only posts sends of a single region, so
+// the message goes out as one element. An alignment of 0 uses the pool's
+fn sge_buffer(rdma: &Rdma, pool: &MrPool, message_size: u32, alignment: u32) -> anyhow::Result<LocalMr> {
+    if alignment == 0 {
+        return message_buffer(rdma, pool, message_size);
+    }
+    let mut lmr = pool.take_aligned(rdma, message_size as usize, alignment as usize)?;
+    let buf = vec![1_u8; message_size as usize];
+    let _num = lmr.as_mut_slice().write(buf.as_slice())?;
+    Ok(lmr)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn send_with_imm(rdma: &Rdma, lmr: &LocalMr, message_size: u32, seqs: Range<u32>, tx_depth: usize, recorder: &Recorder<'_>, imm_mode: ServerImmMode, imm_data: u32, id: u32) -> anyhow::Result<()> {
+    let message = &lmr.get(0..message_size as usize)?;
+    post(seqs, tx_depth, recorder, |seq| async move {
+        rdma.send_with_imm(message, protocol::immediate(imm_mode, imm_data, id, seq)).await?;
+        Ok(())
+    }).await
+}
+
+// send_mixed alternates plain sends and sends with immediate data,
+// messages at even positions of the connection are plain
+#[allow(clippy::too_many_arguments)]
+pub async fn send_mixed(rdma: &Rdma, lmr: &LocalMr, message_size: u32, seqs: Range<u32>, tx_depth: usize, recorder: &Recorder<'_>, imm_mode: ServerImmMode, imm_data: u32, id: u32) -> anyhow::Result<()> {
+    let message = &lmr.get(0..message_size as usize)?;
+    post(seqs, tx_depth, recorder, |seq| async move {
+        if seq % 2 == 0 {
+            rdma.send(message).await?;
+        } else {
+            rdma.send_with_imm(message, protocol::immediate(imm_mode, imm_data, id, seq / 2)).await?;
+        }
+        Ok(())
+    }).await
+}
+
 #[tonic::async_trait]
 impl Listener for Initiator {
     async fn send(
         &self,
         request: Request<SendRequest>,
     ) -> Result<Response<SendReply>, Status> {
+        let _timer = metrics::grpc("/listener.Listener/Send");
+        let span = info_span!("send", job_id = request.get_ref().id);
+        telemetry::join(&span, request.metadata());
         let request = request.into_inner();
         
-        if let Err(e) = initiate(request).await{
-            error!("initiate error: {:?}", e);
-            return Err(Status::internal(e.to_string()));
-        }
-        
+        let qos = match initiate(request, self.device.clone(), self.domains.clone(), self.jobs.clone()).instrument(span).await{
+            Ok(qos) => qos,
+            Err(e) => {
+                error!("initiate error: {:?}", e);
+                return Err(Status::internal(e.to_string()));
+            }
+        };
 
         let reply = SendReply{
             message: "all good".to_string(),
+            traffic_class: qos.traffic_class,
+            service_level: qos.service_level,
+            flow_label: qos.flow_label,
         };
 
         Ok(Response::new(reply))
     }
+    async fn get_job(
+        &self,
+        request: Request<JobRequest>,
+    ) -> Result<Response<JobStatus>, Status> {
+        let _timer = metrics::grpc("/listener.Listener/GetJob");
+        let id = request.get_ref().id;
+        match self.jobs.get(id) {
+            Some(job) => Ok(Response::new(job)),
+            None => Err(Status::not_found(format!("job {} not found", id))),
+        }
+    }
+    async fn cancel_job(
+        &self,
+        request: Request<JobRequest>,
+    ) -> Result<Response<JobStatus>, Status> {
+        let _timer = metrics::grpc("/listener.Listener/CancelJob");
+        let id = request.get_ref().id;
+        match self.jobs.cancel(id) {
+            Ok(job) => {
+                info!("job {} cancelled", id);
+                Ok(Response::new(job))
+            },
+            Err(e) if self.jobs.get(id).is_none() => Err(Status::not_found(e.to_string())),
+            Err(e) => Err(Status::failed_precondition(e.to_string())),
+        }
+    }
+    async fn list_jobs(
+        &self,
+        _request: Request<ListJobsRequest>,
+    ) -> Result<Response<JobList>, Status> {
+        let _timer = metrics::grpc("/listener.Listener/ListJobs");
+        Ok(Response::new(JobList{
+            jobs: self.jobs.list(),
+        }))
+    }
+    type WatchJobStream = Pin<Box<dyn Stream<Item = Result<JobStatus, Status>> + Send>>;
+    async fn watch_job(
+        &self,
+        request: Request<JobRequest>,
+    ) -> Result<Response<Self::WatchJobStream>, Status> {
+        let _timer = metrics::grpc("/listener.Listener/WatchJob");
+        let id = request.get_ref().id;
+        if self.jobs.get(id).is_none() {
+            return Err(Status::not_found(format!("job {} not found", id)));
+        }
+        // the state is the registry and the last status sent, it is None
+        // once a finished job was sent
+        let stream = stream::unfold(Some((self.jobs.clone(), None)), move |state| async move {
+            let (jobs, last): (Jobs, Option<JobStatus>) = state?;
+            loop {
+                let job = jobs.get(id)?;
+                if last.as_ref() != Some(&job) {
+                    let next = if job.state == JobState::JobRunning as i32 {
+                        Some((jobs, Some(job.clone())))
+                    } else {
+                        None
+                    };
+                    return Some((Ok(job), next));
+                }
+                tokio::time::sleep(WATCH_PERIOD).await;
+            }
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+    async fn query_results(
+        &self,
+        request: Request<QueryResultsRequest>,
+    ) -> Result<Response<QueryResultsReply>, Status> {
+        let _timer = metrics::grpc("/listener.Listener/QueryResults");
+        let store = self.store.as_ref().ok_or_else(|| Status::failed_precondition("the initiator has no results database"))?;
+        match store.query(request.into_inner()).await {
+            Ok(results) => Ok(Response::new(QueryResultsReply{ results })),
+            Err(e) => Err(Status::internal(e.to_string())),
+        }
+    }
+    async fn get_capabilities(
+        &self,
+        _request: Request<CapabilitiesRequest>,
+    ) -> Result<Response<CapabilitiesReply>, Status> {
+        let _timer = metrics::grpc("/listener.Listener/GetCapabilities");
+        Ok(Response::new(initiator_capabilities()))
+    }
+}
+
+// initiator_capabilities describes what the initiator can send, agents
+// report it to the controller as well
+pub fn initiator_capabilities() -> CapabilitiesReply {
+    CapabilitiesReply{
+        version: capabilities::VERSION.to_string(),
+        operations: vec![
+            Operation::Send.into(),
+            Operation::SendWithImm.into(),
+            Operation::SendMixed.into(),
+        ],
+        transports: capabilities::TRANSPORTS.iter().map(|t| t.to_string()).collect(),
+        mtus: vec![
+            Mtu::Mtu512.into(),
+            Mtu::Mtu1024.into(),
+            Mtu::Mtu2048.into(),
+            Mtu::Mtu4096.into(),
+        ],
+        max_message_size: capabilities::MAX_MESSAGE_SIZE,
+        devices: capabilities::devices().into_iter().map(Device::from).collect(),
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explicit_sweep_sizes_keep_their_order_without_duplicates() {
+        let request = SendRequest{ sweep_sizes: vec![64, 8, 64, 4096, 8], ..Default::default() };
+        assert_eq!(sweep_sizes(&request).unwrap(), vec![64, 8, 4096]);
+    }
+
+    #[test]
+    fn sweep_ranges_double() {
+        let request = SendRequest{ sweep_min: 3, sweep_max: 30, ..Default::default() };
+        assert_eq!(sweep_sizes(&request).unwrap(), vec![3, 6, 12, 24]);
+        let request = SendRequest{ sweep_max: 8, ..Default::default() };
+        assert_eq!(sweep_sizes(&request).unwrap(), vec![2, 4, 8]);
+        let request = SendRequest{ message_size: 100, ..Default::default() };
+        assert_eq!(sweep_sizes(&request).unwrap(), vec![100]);
+        let request = SendRequest{ sweep_max: u32::MAX, sweep_min: 1 << 31, ..Default::default() };
+        assert_eq!(sweep_sizes(&request).unwrap(), vec![1 << 31]);
+    }
+
+    #[test]
+    fn invalid_sweeps_are_rejected() {
+        assert!(sweep_sizes(&SendRequest{ sweep_sizes: vec![8, 0], ..Default::default() }).is_err());
+        assert!(sweep_sizes(&SendRequest{ sweep_min: 16, sweep_max: 8, ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn matrix_cells_expand_in_order() {
+        let request = SendRequest{ message_size: 8, mtu: Mtu::Mtu1024.into(), ..Default::default() };
+        let matrix = Matrix{
+            mtus: vec![Mtu::Mtu1024.into(), Mtu::Mtu4096.into(), Mtu::Mtu1024.into()],
+            message_sizes: vec![64, 4096, 64],
+            tx_depths: vec![1, 8],
+            connections: vec![2],
+        };
+        let cells = matrix_cells(&request, &matrix).unwrap().into_iter().
+            map(|(request, connections)| (request.mtu, request.message_size, request.tx_depth, connections)).collect::<Vec<_>>();
+        let (mtu1024, mtu4096) = (Mtu::Mtu1024 as i32, Mtu::Mtu4096 as i32);
+        assert_eq!(cells, vec![
+            (mtu1024, 64, 1, 2), (mtu1024, 64, 8, 2), (mtu1024, 4096, 1, 2), (mtu1024, 4096, 8, 2),
+            (mtu4096, 64, 1, 2), (mtu4096, 64, 8, 2), (mtu4096, 4096, 1, 2), (mtu4096, 4096, 8, 2),
+        ]);
+    }
+
+    #[test]
+    fn matrix_cells_default_to_the_request() {
+        let request = SendRequest{ message_size: 512, mtu: Mtu::Mtu2048.into(), tx_depth: 4, ..Default::default() };
+        let cells = matrix_cells(&request, &Matrix::default()).unwrap();
+        assert_eq!(cells.len(), 1);
+        let (cell, connections) = &cells[0];
+        assert_eq!((cell.mtu, cell.message_size, cell.tx_depth, *connections), (Mtu::Mtu2048 as i32, 512, 4, 1));
+        assert!(cell.matrix.is_none());
+    }
+
+    #[test]
+    fn invalid_matrices_are_rejected() {
+        let request = SendRequest{ message_size: 8, ..Default::default() };
+        assert!(matrix_cells(&request, &Matrix{ connections: vec![0], ..Default::default() }).is_err());
+        let request = SendRequest{ sweep_max: 64, ..Default::default() };
+        assert!(matrix_cells(&request, &Matrix::default()).is_err());
+    }
+}
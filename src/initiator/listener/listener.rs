@@ -13,18 +13,488 @@ pub struct SendRequest {
     pub message_size: u32,
     #[prost(enumeration = "Mtu", tag = "6")]
     pub mtu: i32,
+    #[prost(enumeration = "ImmMode", tag = "7")]
+    pub imm_mode: i32,
+    #[prost(uint32, tag = "8")]
+    pub imm_data: u32,
+    #[prost(string, optional, tag = "9")]
+    pub device: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(uint32, optional, tag = "10")]
+    pub ib_port: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "11")]
+    pub gid_index: ::core::option::Option<u32>,
+    #[prost(string, optional, tag = "12")]
+    pub server_device: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(uint32, optional, tag = "13")]
+    pub server_ib_port: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "14")]
+    pub server_gid_index: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "15")]
+    pub max_send_wr: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "16")]
+    pub max_recv_wr: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "17")]
+    pub max_sge: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "18")]
+    pub cq_size: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "19")]
+    pub max_inline_data: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "20")]
+    pub retry_count: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "21")]
+    pub rnr_retry: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "22")]
+    pub timeout: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "23")]
+    pub min_rnr_timer: ::core::option::Option<u32>,
+    /// async-rdma can't set the address vector of a queue pair, jobs setting
+    /// any of these are rejected
+    #[prost(uint32, optional, tag = "24")]
+    pub traffic_class: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "25")]
+    pub dscp: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "26")]
+    pub service_level: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "27")]
+    pub flow_label: ::core::option::Option<u32>,
+    #[prost(enumeration = "ConnectionType", tag = "28")]
+    pub connection_type: i32,
+    /// the elements of a scatter-gather layout lie back to back in one region
+    /// and are posted as a single element, async-rdma has no multi-element sends
+    #[prost(uint32, tag = "29")]
+    pub sge_count: u32,
+    #[prost(uint32, repeated, tag = "30")]
+    pub sge_sizes: ::prost::alloc::vec::Vec<u32>,
+    #[prost(uint32, tag = "31")]
+    pub sge_alignment: u32,
+    #[prost(uint32, repeated, tag = "32")]
+    pub sweep_sizes: ::prost::alloc::vec::Vec<u32>,
+    #[prost(uint32, tag = "33")]
+    pub sweep_min: u32,
+    #[prost(uint32, tag = "34")]
+    pub sweep_max: u32,
+    #[prost(uint32, tag = "35")]
+    pub tx_depth: u32,
+    #[prost(message, optional, tag = "36")]
+    pub matrix: ::core::option::Option<Matrix>,
+    #[prost(uint32, tag = "37")]
+    pub warmup_messages: u32,
+    #[prost(uint32, tag = "38")]
+    pub repetitions: u32,
+    /// report interval in milliseconds, 0 disables interval reports
+    #[prost(uint32, tag = "39")]
+    pub interval_ms: u32,
+    /// messages per second on every connection, 0 sends as fast as tx depth
+    /// allows
+    #[prost(uint32, tag = "40")]
+    pub message_rate: u32,
+    /// wall clock time in nanoseconds since the unix epoch at which the job
+    /// starts sending, 0 starts right away. Jobs of several initiators start
+    /// together as far as the clocks of their hosts agree
+    #[prost(uint64, tag = "41")]
+    pub start_at_unix_ns: u64,
+    /// labels like "rack=a3" kept with the results of the job
+    #[prost(string, repeated, tag = "42")]
+    pub tags: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// the server gives up on the messages of a connection when none arrived
+    /// for this long, 0 uses the server default
+    #[prost(uint32, tag = "43")]
+    pub receive_timeout_ms: u32,
+    /// the server doesn't check the immediate data of the messages
+    #[prost(bool, tag = "44")]
+    pub skip_integrity_check: bool,
+}
+/// Matrix runs every combination of its dimensions as a cell of one job,
+/// empty dimensions and unset warmup or repetitions use the value of the request
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Matrix {
+    #[prost(enumeration = "Mtu", repeated, tag = "1")]
+    pub mtus: ::prost::alloc::vec::Vec<i32>,
+    #[prost(uint32, repeated, tag = "2")]
+    pub message_sizes: ::prost::alloc::vec::Vec<u32>,
+    #[prost(uint32, repeated, tag = "3")]
+    pub tx_depths: ::prost::alloc::vec::Vec<u32>,
+    #[prost(uint32, repeated, tag = "4")]
+    pub connections: ::prost::alloc::vec::Vec<u32>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SendReply {
     #[prost(string, tag = "1")]
     pub message: ::prost::alloc::string::String,
+    #[prost(uint32, optional, tag = "2")]
+    pub traffic_class: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "3")]
+    pub service_level: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "4")]
+    pub flow_label: ::core::option::Option<u32>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JobRequest {
+    #[prost(uint32, tag = "1")]
+    pub id: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListJobsRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JobList {
+    #[prost(message, repeated, tag = "1")]
+    pub jobs: ::prost::alloc::vec::Vec<JobSummary>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JobSummary {
+    #[prost(uint32, tag = "1")]
+    pub id: u32,
+    #[prost(enumeration = "JobState", tag = "2")]
+    pub state: i32,
+    #[prost(string, tag = "3")]
+    pub error: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JobStatus {
+    #[prost(uint32, tag = "1")]
+    pub id: u32,
+    #[prost(enumeration = "JobState", tag = "2")]
+    pub state: i32,
+    #[prost(message, repeated, tag = "3")]
+    pub measurements: ::prost::alloc::vec::Vec<Measurement>,
+    #[prost(string, tag = "4")]
+    pub error: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "5")]
+    pub cells: ::prost::alloc::vec::Vec<Cell>,
+    /// completion latencies of all measured messages of the job on the
+    /// initiator and the time the server's receives waited for them, which
+    /// includes idle time between messages
+    #[prost(message, optional, tag = "6")]
+    pub latency: ::core::option::Option<Latency>,
+    #[prost(message, optional, tag = "7")]
+    pub server_receive_wait: ::core::option::Option<Latency>,
+    #[prost(message, repeated, tag = "8")]
+    pub intervals: ::prost::alloc::vec::Vec<Interval>,
+    /// unset if the server does not report what it received
+    #[prost(message, optional, tag = "9")]
+    pub delivery: ::core::option::Option<Delivery>,
+    /// wall clock times in nanoseconds since the unix epoch the data path
+    /// started sending and sent its last message, 0 until then
+    #[prost(uint64, tag = "10")]
+    pub started_unix_ns: u64,
+    #[prost(uint64, tag = "11")]
+    pub sent_unix_ns: u64,
+}
+/// Delivery compares the messages the initiator sent, including warmup
+/// messages, with what the server received
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Delivery {
+    #[prost(uint64, tag = "1")]
+    pub sent: u64,
+    #[prost(uint64, tag = "2")]
+    pub received: u64,
+    #[prost(uint64, tag = "3")]
+    pub receive_errors: u64,
+    #[prost(uint64, tag = "4")]
+    pub integrity_errors: u64,
+}
+/// Interval holds the messages completed in one report interval of a job,
+/// offsets are relative to the start of the job
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Interval {
+    #[prost(uint64, tag = "1")]
+    pub start_ns: u64,
+    #[prost(uint64, tag = "2")]
+    pub elapsed_ns: u64,
+    #[prost(uint64, tag = "3")]
+    pub messages: u64,
+    #[prost(uint64, tag = "4")]
+    pub bytes: u64,
+    #[prost(double, tag = "5")]
+    pub bandwidth_gbps: f64,
+    #[prost(double, tag = "6")]
+    pub message_rate: f64,
+    #[prost(uint64, tag = "7")]
+    pub errors: u64,
+}
+/// Cell holds the trials of one message size of a job or of one
+/// combination of a matrix job
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Cell {
+    #[prost(enumeration = "Mtu", tag = "1")]
+    pub mtu: i32,
+    #[prost(uint32, tag = "2")]
+    pub message_size: u32,
+    #[prost(uint32, tag = "3")]
+    pub tx_depth: u32,
+    #[prost(uint32, tag = "4")]
+    pub connections: u32,
+    #[prost(message, repeated, tag = "5")]
+    pub trials: ::prost::alloc::vec::Vec<Measurement>,
+    #[prost(message, optional, tag = "6")]
+    pub bandwidth_gbps: ::core::option::Option<Stats>,
+    #[prost(message, optional, tag = "7")]
+    pub time_per_message_us: ::core::option::Option<Stats>,
+    #[prost(message, optional, tag = "8")]
+    pub latency: ::core::option::Option<Latency>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Stats {
+    #[prost(double, tag = "1")]
+    pub mean: f64,
+    #[prost(double, tag = "2")]
+    pub stddev: f64,
+    #[prost(double, tag = "3")]
+    pub min: f64,
+    #[prost(double, tag = "4")]
+    pub max: f64,
+    /// half width of the 95% confidence interval of the mean
+    #[prost(double, tag = "5")]
+    pub ci95: f64,
+}
+/// Measurement holds the result of one message size of a job, summed over
+/// its trials, or of a single trial
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Measurement {
+    #[prost(uint32, tag = "1")]
+    pub message_size: u32,
+    #[prost(uint32, tag = "2")]
+    pub messages: u32,
+    #[prost(uint64, tag = "3")]
+    pub bytes: u64,
+    #[prost(uint64, tag = "4")]
+    pub elapsed_ns: u64,
+    #[prost(double, tag = "5")]
+    pub bandwidth_gbps: f64,
+    /// elapsed time divided by messages, per message latencies are in latency
+    #[prost(double, tag = "6")]
+    pub time_per_message_us: f64,
+    #[prost(message, optional, tag = "7")]
+    pub latency: ::core::option::Option<Latency>,
+}
+/// Latency is an HDR histogram of per message latencies in nanoseconds.
+/// Histograms of several connections or initiators are combined by decoding
+/// and adding them, the percentiles are derived from the histogram
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Latency {
+    /// V2 deflate encoded HDR histogram
+    #[prost(bytes = "vec", tag = "1")]
+    pub histogram: ::prost::alloc::vec::Vec<u8>,
+    #[prost(uint64, tag = "2")]
+    pub count: u64,
+    #[prost(double, tag = "3")]
+    pub p50_us: f64,
+    #[prost(double, tag = "4")]
+    pub p99_us: f64,
+    #[prost(double, tag = "5")]
+    pub p999_us: f64,
+    #[prost(double, tag = "6")]
+    pub max_us: f64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CapabilitiesRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CapabilitiesReply {
+    #[prost(string, tag = "1")]
+    pub version: ::prost::alloc::string::String,
+    #[prost(enumeration = "Operation", repeated, tag = "2")]
+    pub operations: ::prost::alloc::vec::Vec<i32>,
+    #[prost(string, repeated, tag = "3")]
+    pub transports: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(enumeration = "Mtu", repeated, tag = "4")]
+    pub mtus: ::prost::alloc::vec::Vec<i32>,
+    #[prost(uint32, tag = "5")]
+    pub max_message_size: u32,
+    #[prost(message, repeated, tag = "6")]
+    pub devices: ::prost::alloc::vec::Vec<Device>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Device {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "2")]
+    pub port: u32,
+    #[prost(string, tag = "3")]
+    pub state: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub link_layer: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub rate: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RegisterRequest {
+    /// address the controller and other agents reach the agent at
+    #[prost(string, tag = "1")]
+    pub address: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "2")]
+    pub server_port: u32,
+    #[prost(uint32, tag = "3")]
+    pub initiator_port: u32,
+    #[prost(message, optional, tag = "4")]
+    pub capabilities: ::core::option::Option<CapabilitiesReply>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RegisterReply {
+    /// address:initiatorPort of the agent
+    #[prost(string, tag = "1")]
+    pub agent_id: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "2")]
+    pub heartbeat_interval_ms: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HeartbeatRequest {
+    #[prost(string, tag = "1")]
+    pub agent_id: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "2")]
+    pub running_jobs: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HeartbeatReply {
+    /// false once the controller forgot the agent, which then registers again
+    #[prost(bool, tag = "1")]
+    pub registered: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListAgentsRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AgentList {
+    #[prost(message, repeated, tag = "1")]
+    pub agents: ::prost::alloc::vec::Vec<Agent>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Agent {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub address: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "3")]
+    pub server_port: u32,
+    #[prost(uint32, tag = "4")]
+    pub initiator_port: u32,
+    #[prost(message, optional, tag = "5")]
+    pub capabilities: ::core::option::Option<CapabilitiesReply>,
+    #[prost(enumeration = "AgentState", tag = "6")]
+    pub state: i32,
+    #[prost(uint32, tag = "7")]
+    pub running_jobs: u32,
+    #[prost(uint64, tag = "8")]
+    pub registered_unix_ms: u64,
+    #[prost(uint64, tag = "9")]
+    pub last_heartbeat_unix_ms: u64,
+}
+/// DispatchRequest runs request on the initiator of one agent against the
+/// server of another, the address of the request is replaced by the server
+/// of that agent. A request id of 0 lets the controller pick one its
+/// initiator doesn't know, ids from 2^31 up are reserved for those
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DispatchRequest {
+    #[prost(string, tag = "1")]
+    pub initiator: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub server: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "3")]
+    pub request: ::core::option::Option<SendRequest>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DispatchReply {
+    #[prost(uint32, tag = "1")]
+    pub id: u32,
+    #[prost(message, optional, tag = "2")]
+    pub reply: ::core::option::Option<SendReply>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DispatchedJobList {
+    #[prost(message, repeated, tag = "1")]
+    pub jobs: ::prost::alloc::vec::Vec<DispatchedJob>,
+}
+/// DispatchedJob is the latest status of a dispatched job the controller got
+/// from its initiator, error is set if the controller lost track of it
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DispatchedJob {
+    #[prost(uint32, tag = "1")]
+    pub id: u32,
+    #[prost(string, tag = "2")]
+    pub initiator: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub server: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "4")]
+    pub status: ::core::option::Option<JobStatus>,
+    #[prost(string, tag = "5")]
+    pub error: ::prost::alloc::string::String,
+}
+/// QueryResultsRequest filters the stored results, unset fields match every
+/// result. Hosts match their address with or without the port, a result
+/// matches the tags if it has all of them
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct QueryResultsRequest {
+    #[prost(uint64, tag = "1")]
+    pub from_unix_ms: u64,
+    #[prost(uint64, tag = "2")]
+    pub to_unix_ms: u64,
+    #[prost(string, tag = "3")]
+    pub initiator: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub server: ::prost::alloc::string::String,
+    #[prost(enumeration = "Operation", optional, tag = "5")]
+    pub op: ::core::option::Option<i32>,
+    #[prost(uint32, tag = "6")]
+    pub message_size: u32,
+    #[prost(string, repeated, tag = "7")]
+    pub tags: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// most recent results returned, 0 returns up to 1000
+    #[prost(uint32, tag = "8")]
+    pub limit: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct QueryResultsReply {
+    #[prost(message, repeated, tag = "1")]
+    pub results: ::prost::alloc::vec::Vec<StoredResult>,
+}
+/// StoredResult is a finished job with the request it ran
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StoredResult {
+    #[prost(uint64, tag = "1")]
+    pub id: u64,
+    #[prost(uint64, tag = "2")]
+    pub finished_unix_ms: u64,
+    #[prost(string, tag = "3")]
+    pub initiator: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "4")]
+    pub spec: ::core::option::Option<SendRequest>,
+    #[prost(message, optional, tag = "5")]
+    pub status: ::core::option::Option<JobStatus>,
 }
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum Operation {
     Send = 0,
     SendWithImm = 1,
+    SendMixed = 2,
 }
 impl Operation {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -35,6 +505,7 @@ impl Operation {
         match self {
             Operation::Send => "SEND",
             Operation::SendWithImm => "SEND_WITH_IMM",
+            Operation::SendMixed => "SEND_MIXED",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -42,6 +513,99 @@ impl Operation {
         match value {
             "SEND" => Some(Self::Send),
             "SEND_WITH_IMM" => Some(Self::SendWithImm),
+            "SEND_MIXED" => Some(Self::SendMixed),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum JobState {
+    JobRunning = 0,
+    JobCompleted = 1,
+    JobFailed = 2,
+    JobCancelled = 3,
+}
+impl JobState {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            JobState::JobRunning => "JOB_RUNNING",
+            JobState::JobCompleted => "JOB_COMPLETED",
+            JobState::JobFailed => "JOB_FAILED",
+            JobState::JobCancelled => "JOB_CANCELLED",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "JOB_RUNNING" => Some(Self::JobRunning),
+            "JOB_COMPLETED" => Some(Self::JobCompleted),
+            "JOB_FAILED" => Some(Self::JobFailed),
+            "JOB_CANCELLED" => Some(Self::JobCancelled),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ConnectionType {
+    RcTcp = 0,
+    RcCm = 1,
+    /// endpoints exchanged in Init, the queue pair has no async-rdma agent and
+    /// only carries SEND without scatter-gather layouts
+    RcGrpc = 3,
+}
+impl ConnectionType {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            ConnectionType::RcTcp => "RC_TCP",
+            ConnectionType::RcCm => "RC_CM",
+            ConnectionType::RcGrpc => "RC_GRPC",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "RC_TCP" => Some(Self::RcTcp),
+            "RC_CM" => Some(Self::RcCm),
+            "RC_GRPC" => Some(Self::RcGrpc),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ImmMode {
+    ImmConstant = 0,
+    ImmSequence = 1,
+    ImmJobId = 2,
+}
+impl ImmMode {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            ImmMode::ImmConstant => "IMM_CONSTANT",
+            ImmMode::ImmSequence => "IMM_SEQUENCE",
+            ImmMode::ImmJobId => "IMM_JOB_ID",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "IMM_CONSTANT" => Some(Self::ImmConstant),
+            "IMM_SEQUENCE" => Some(Self::ImmSequence),
+            "IMM_JOB_ID" => Some(Self::ImmJobId),
             _ => None,
         }
     }
@@ -78,6 +642,33 @@ impl Mtu {
         }
     }
 }
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum AgentState {
+    AgentAlive = 0,
+    /// missed its heartbeats, jobs are not dispatched to it
+    AgentLost = 1,
+}
+impl AgentState {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            AgentState::AgentAlive => "AGENT_ALIVE",
+            AgentState::AgentLost => "AGENT_LOST",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "AGENT_ALIVE" => Some(Self::AgentAlive),
+            "AGENT_LOST" => Some(Self::AgentLost),
+            _ => None,
+        }
+    }
+}
 /// Generated client implementations.
 pub mod listener_client {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
@@ -182,51 +773,963 @@ pub mod listener_client {
             req.extensions_mut().insert(GrpcMethod::new("listener.Listener", "Send"));
             self.inner.unary(req, path, codec).await
         }
-    }
-}
-/// Generated server implementations.
-pub mod listener_server {
-    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
-    use tonic::codegen::*;
-    /// Generated trait containing gRPC methods that should be implemented for use with ListenerServer.
-    #[async_trait]
-    pub trait Listener: Send + Sync + 'static {
-        async fn send(
-            &self,
-            request: tonic::Request<super::SendRequest>,
-        ) -> std::result::Result<tonic::Response<super::SendReply>, tonic::Status>;
-    }
-    #[derive(Debug)]
-    pub struct ListenerServer<T: Listener> {
-        inner: _Inner<T>,
-        accept_compression_encodings: EnabledCompressionEncodings,
-        send_compression_encodings: EnabledCompressionEncodings,
-        max_decoding_message_size: Option<usize>,
-        max_encoding_message_size: Option<usize>,
-    }
-    struct _Inner<T>(Arc<T>);
-    impl<T: Listener> ListenerServer<T> {
-        pub fn new(inner: T) -> Self {
-            Self::from_arc(Arc::new(inner))
+        pub async fn get_capabilities(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CapabilitiesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CapabilitiesReply>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/listener.Listener/GetCapabilities",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("listener.Listener", "GetCapabilities"));
+            self.inner.unary(req, path, codec).await
         }
-        pub fn from_arc(inner: Arc<T>) -> Self {
-            let inner = _Inner(inner);
-            Self {
-                inner,
-                accept_compression_encodings: Default::default(),
-                send_compression_encodings: Default::default(),
-                max_decoding_message_size: None,
-                max_encoding_message_size: None,
-            }
+        pub async fn get_job(
+            &mut self,
+            request: impl tonic::IntoRequest<super::JobRequest>,
+        ) -> std::result::Result<tonic::Response<super::JobStatus>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/listener.Listener/GetJob");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("listener.Listener", "GetJob"));
+            self.inner.unary(req, path, codec).await
         }
-        pub fn with_interceptor<F>(
-            inner: T,
-            interceptor: F,
-        ) -> InterceptedService<Self, F>
-        where
-            F: tonic::service::Interceptor,
-        {
-            InterceptedService::new(Self::new(inner), interceptor)
+        pub async fn cancel_job(
+            &mut self,
+            request: impl tonic::IntoRequest<super::JobRequest>,
+        ) -> std::result::Result<tonic::Response<super::JobStatus>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/listener.Listener/CancelJob",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("listener.Listener", "CancelJob"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn list_jobs(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListJobsRequest>,
+        ) -> std::result::Result<tonic::Response<super::JobList>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/listener.Listener/ListJobs",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("listener.Listener", "ListJobs"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// WatchJob streams the status of a job whenever it changes until the job
+        /// is finished
+        pub async fn watch_job(
+            &mut self,
+            request: impl tonic::IntoRequest<super::JobRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::JobStatus>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/listener.Listener/WatchJob",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("listener.Listener", "WatchJob"));
+            self.inner.server_streaming(req, path, codec).await
+        }
+        /// QueryResults returns the finished jobs kept in the results database
+        pub async fn query_results(
+            &mut self,
+            request: impl tonic::IntoRequest<super::QueryResultsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::QueryResultsReply>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/listener.Listener/QueryResults",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("listener.Listener", "QueryResults"));
+            self.inner.unary(req, path, codec).await
+        }
+    }
+}
+/// Generated client implementations.
+pub mod controller_client {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    use tonic::codegen::http::Uri;
+    /// Controller keeps an inventory of the agents, rocky-rs processes that
+    /// register with it, dispatches jobs to their initiators and collects the
+    /// results
+    #[derive(Debug, Clone)]
+    pub struct ControllerClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl ControllerClient<tonic::transport::Channel> {
+        /// Attempt to create a new client by connecting to a given endpoint.
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> ControllerClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> ControllerClient<InterceptedService<T, F>>
+        where
+            F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+            >>::Error: Into<StdError> + Send + Sync,
+        {
+            ControllerClient::new(InterceptedService::new(inner, interceptor))
+        }
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
+            self
+        }
+        /// Enable decompressing responses.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_decoding_message_size(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_encoding_message_size(limit);
+            self
+        }
+        pub async fn register(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RegisterRequest>,
+        ) -> std::result::Result<tonic::Response<super::RegisterReply>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/listener.Controller/Register",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("listener.Controller", "Register"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn heartbeat(
+            &mut self,
+            request: impl tonic::IntoRequest<super::HeartbeatRequest>,
+        ) -> std::result::Result<tonic::Response<super::HeartbeatReply>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/listener.Controller/Heartbeat",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("listener.Controller", "Heartbeat"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn list_agents(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListAgentsRequest>,
+        ) -> std::result::Result<tonic::Response<super::AgentList>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/listener.Controller/ListAgents",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("listener.Controller", "ListAgents"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn dispatch(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DispatchRequest>,
+        ) -> std::result::Result<tonic::Response<super::DispatchReply>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/listener.Controller/Dispatch",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("listener.Controller", "Dispatch"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn get_job(
+            &mut self,
+            request: impl tonic::IntoRequest<super::JobRequest>,
+        ) -> std::result::Result<tonic::Response<super::DispatchedJob>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/listener.Controller/GetJob",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("listener.Controller", "GetJob"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn list_jobs(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListJobsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::DispatchedJobList>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/listener.Controller/ListJobs",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("listener.Controller", "ListJobs"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// QueryResults returns the collected jobs kept in the results database
+        pub async fn query_results(
+            &mut self,
+            request: impl tonic::IntoRequest<super::QueryResultsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::QueryResultsReply>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/listener.Controller/QueryResults",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("listener.Controller", "QueryResults"));
+            self.inner.unary(req, path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod listener_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with ListenerServer.
+    #[async_trait]
+    pub trait Listener: Send + Sync + 'static {
+        async fn send(
+            &self,
+            request: tonic::Request<super::SendRequest>,
+        ) -> std::result::Result<tonic::Response<super::SendReply>, tonic::Status>;
+        async fn get_capabilities(
+            &self,
+            request: tonic::Request<super::CapabilitiesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CapabilitiesReply>,
+            tonic::Status,
+        >;
+        async fn get_job(
+            &self,
+            request: tonic::Request<super::JobRequest>,
+        ) -> std::result::Result<tonic::Response<super::JobStatus>, tonic::Status>;
+        async fn cancel_job(
+            &self,
+            request: tonic::Request<super::JobRequest>,
+        ) -> std::result::Result<tonic::Response<super::JobStatus>, tonic::Status>;
+        async fn list_jobs(
+            &self,
+            request: tonic::Request<super::ListJobsRequest>,
+        ) -> std::result::Result<tonic::Response<super::JobList>, tonic::Status>;
+        /// Server streaming response type for the WatchJob method.
+        type WatchJobStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::JobStatus, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        /// WatchJob streams the status of a job whenever it changes until the job
+        /// is finished
+        async fn watch_job(
+            &self,
+            request: tonic::Request<super::JobRequest>,
+        ) -> std::result::Result<tonic::Response<Self::WatchJobStream>, tonic::Status>;
+        /// QueryResults returns the finished jobs kept in the results database
+        async fn query_results(
+            &self,
+            request: tonic::Request<super::QueryResultsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::QueryResultsReply>,
+            tonic::Status,
+        >;
+    }
+    #[derive(Debug)]
+    pub struct ListenerServer<T: Listener> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: Listener> ListenerServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for ListenerServer<T>
+    where
+        T: Listener,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/listener.Listener/Send" => {
+                    #[allow(non_camel_case_types)]
+                    struct SendSvc<T: Listener>(pub Arc<T>);
+                    impl<T: Listener> tonic::server::UnaryService<super::SendRequest>
+                    for SendSvc<T> {
+                        type Response = super::SendReply;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SendRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Listener>::send(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = SendSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/listener.Listener/GetCapabilities" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetCapabilitiesSvc<T: Listener>(pub Arc<T>);
+                    impl<
+                        T: Listener,
+                    > tonic::server::UnaryService<super::CapabilitiesRequest>
+                    for GetCapabilitiesSvc<T> {
+                        type Response = super::CapabilitiesReply;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CapabilitiesRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Listener>::get_capabilities(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetCapabilitiesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/listener.Listener/GetJob" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetJobSvc<T: Listener>(pub Arc<T>);
+                    impl<T: Listener> tonic::server::UnaryService<super::JobRequest>
+                    for GetJobSvc<T> {
+                        type Response = super::JobStatus;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::JobRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Listener>::get_job(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetJobSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/listener.Listener/CancelJob" => {
+                    #[allow(non_camel_case_types)]
+                    struct CancelJobSvc<T: Listener>(pub Arc<T>);
+                    impl<T: Listener> tonic::server::UnaryService<super::JobRequest>
+                    for CancelJobSvc<T> {
+                        type Response = super::JobStatus;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::JobRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Listener>::cancel_job(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = CancelJobSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/listener.Listener/ListJobs" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListJobsSvc<T: Listener>(pub Arc<T>);
+                    impl<T: Listener> tonic::server::UnaryService<super::ListJobsRequest>
+                    for ListJobsSvc<T> {
+                        type Response = super::JobList;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListJobsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Listener>::list_jobs(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ListJobsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/listener.Listener/WatchJob" => {
+                    #[allow(non_camel_case_types)]
+                    struct WatchJobSvc<T: Listener>(pub Arc<T>);
+                    impl<
+                        T: Listener,
+                    > tonic::server::ServerStreamingService<super::JobRequest>
+                    for WatchJobSvc<T> {
+                        type Response = super::JobStatus;
+                        type ResponseStream = T::WatchJobStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::JobRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Listener>::watch_job(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = WatchJobSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/listener.Listener/QueryResults" => {
+                    #[allow(non_camel_case_types)]
+                    struct QueryResultsSvc<T: Listener>(pub Arc<T>);
+                    impl<
+                        T: Listener,
+                    > tonic::server::UnaryService<super::QueryResultsRequest>
+                    for QueryResultsSvc<T> {
+                        type Response = super::QueryResultsReply;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::QueryResultsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Listener>::query_results(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = QueryResultsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        Ok(
+                            http::Response::builder()
+                                .status(200)
+                                .header("grpc-status", "12")
+                                .header("content-type", "application/grpc")
+                                .body(empty_body())
+                                .unwrap(),
+                        )
+                    })
+                }
+            }
+        }
+    }
+    impl<T: Listener> Clone for ListenerServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+                max_decoding_message_size: self.max_decoding_message_size,
+                max_encoding_message_size: self.max_encoding_message_size,
+            }
+        }
+    }
+    impl<T: Listener> Clone for _Inner<T> {
+        fn clone(&self) -> Self {
+            Self(Arc::clone(&self.0))
+        }
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+    impl<T: Listener> tonic::server::NamedService for ListenerServer<T> {
+        const NAME: &'static str = "listener.Listener";
+    }
+}
+/// Generated server implementations.
+pub mod controller_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with ControllerServer.
+    #[async_trait]
+    pub trait Controller: Send + Sync + 'static {
+        async fn register(
+            &self,
+            request: tonic::Request<super::RegisterRequest>,
+        ) -> std::result::Result<tonic::Response<super::RegisterReply>, tonic::Status>;
+        async fn heartbeat(
+            &self,
+            request: tonic::Request<super::HeartbeatRequest>,
+        ) -> std::result::Result<tonic::Response<super::HeartbeatReply>, tonic::Status>;
+        async fn list_agents(
+            &self,
+            request: tonic::Request<super::ListAgentsRequest>,
+        ) -> std::result::Result<tonic::Response<super::AgentList>, tonic::Status>;
+        async fn dispatch(
+            &self,
+            request: tonic::Request<super::DispatchRequest>,
+        ) -> std::result::Result<tonic::Response<super::DispatchReply>, tonic::Status>;
+        async fn get_job(
+            &self,
+            request: tonic::Request<super::JobRequest>,
+        ) -> std::result::Result<tonic::Response<super::DispatchedJob>, tonic::Status>;
+        async fn list_jobs(
+            &self,
+            request: tonic::Request<super::ListJobsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::DispatchedJobList>,
+            tonic::Status,
+        >;
+        /// QueryResults returns the collected jobs kept in the results database
+        async fn query_results(
+            &self,
+            request: tonic::Request<super::QueryResultsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::QueryResultsReply>,
+            tonic::Status,
+        >;
+    }
+    /// Controller keeps an inventory of the agents, rocky-rs processes that
+    /// register with it, dispatches jobs to their initiators and collects the
+    /// results
+    #[derive(Debug)]
+    pub struct ControllerServer<T: Controller> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: Controller> ControllerServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
         }
         /// Enable decompressing requests with the given encoding.
         #[must_use]
@@ -257,9 +1760,9 @@ pub mod listener_server {
             self
         }
     }
-    impl<T, B> tonic::codegen::Service<http::Request<B>> for ListenerServer<T>
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for ControllerServer<T>
     where
-        T: Listener,
+        T: Controller,
         B: Body + Send + 'static,
         B::Error: Into<StdError> + Send + 'static,
     {
@@ -275,23 +1778,25 @@ pub mod listener_server {
         fn call(&mut self, req: http::Request<B>) -> Self::Future {
             let inner = self.inner.clone();
             match req.uri().path() {
-                "/listener.Listener/Send" => {
+                "/listener.Controller/Register" => {
                     #[allow(non_camel_case_types)]
-                    struct SendSvc<T: Listener>(pub Arc<T>);
-                    impl<T: Listener> tonic::server::UnaryService<super::SendRequest>
-                    for SendSvc<T> {
-                        type Response = super::SendReply;
+                    struct RegisterSvc<T: Controller>(pub Arc<T>);
+                    impl<
+                        T: Controller,
+                    > tonic::server::UnaryService<super::RegisterRequest>
+                    for RegisterSvc<T> {
+                        type Response = super::RegisterReply;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::SendRequest>,
+                            request: tonic::Request<super::RegisterRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as Listener>::send(&inner, request).await
+                                <T as Controller>::register(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -303,7 +1808,281 @@ pub mod listener_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = SendSvc(inner);
+                        let method = RegisterSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/listener.Controller/Heartbeat" => {
+                    #[allow(non_camel_case_types)]
+                    struct HeartbeatSvc<T: Controller>(pub Arc<T>);
+                    impl<
+                        T: Controller,
+                    > tonic::server::UnaryService<super::HeartbeatRequest>
+                    for HeartbeatSvc<T> {
+                        type Response = super::HeartbeatReply;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::HeartbeatRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Controller>::heartbeat(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = HeartbeatSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/listener.Controller/ListAgents" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListAgentsSvc<T: Controller>(pub Arc<T>);
+                    impl<
+                        T: Controller,
+                    > tonic::server::UnaryService<super::ListAgentsRequest>
+                    for ListAgentsSvc<T> {
+                        type Response = super::AgentList;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListAgentsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Controller>::list_agents(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ListAgentsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/listener.Controller/Dispatch" => {
+                    #[allow(non_camel_case_types)]
+                    struct DispatchSvc<T: Controller>(pub Arc<T>);
+                    impl<
+                        T: Controller,
+                    > tonic::server::UnaryService<super::DispatchRequest>
+                    for DispatchSvc<T> {
+                        type Response = super::DispatchReply;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DispatchRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Controller>::dispatch(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = DispatchSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/listener.Controller/GetJob" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetJobSvc<T: Controller>(pub Arc<T>);
+                    impl<T: Controller> tonic::server::UnaryService<super::JobRequest>
+                    for GetJobSvc<T> {
+                        type Response = super::DispatchedJob;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::JobRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Controller>::get_job(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetJobSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/listener.Controller/ListJobs" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListJobsSvc<T: Controller>(pub Arc<T>);
+                    impl<
+                        T: Controller,
+                    > tonic::server::UnaryService<super::ListJobsRequest>
+                    for ListJobsSvc<T> {
+                        type Response = super::DispatchedJobList;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListJobsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Controller>::list_jobs(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ListJobsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/listener.Controller/QueryResults" => {
+                    #[allow(non_camel_case_types)]
+                    struct QueryResultsSvc<T: Controller>(pub Arc<T>);
+                    impl<
+                        T: Controller,
+                    > tonic::server::UnaryService<super::QueryResultsRequest>
+                    for QueryResultsSvc<T> {
+                        type Response = super::QueryResultsReply;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::QueryResultsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Controller>::query_results(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = QueryResultsSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -334,7 +2113,7 @@ pub mod listener_server {
             }
         }
     }
-    impl<T: Listener> Clone for ListenerServer<T> {
+    impl<T: Controller> Clone for ControllerServer<T> {
         fn clone(&self) -> Self {
             let inner = self.inner.clone();
             Self {
@@ -346,7 +2125,7 @@ pub mod listener_server {
             }
         }
     }
-    impl<T: Listener> Clone for _Inner<T> {
+    impl<T: Controller> Clone for _Inner<T> {
         fn clone(&self) -> Self {
             Self(Arc::clone(&self.0))
         }
@@ -356,7 +2135,7 @@ pub mod listener_server {
             write!(f, "{:?}", self.0)
         }
     }
-    impl<T: Listener> tonic::server::NamedService for ListenerServer<T> {
-        const NAME: &'static str = "listener.Listener";
+    impl<T: Controller> tonic::server::NamedService for ControllerServer<T> {
+        const NAME: &'static str = "listener.Controller";
     }
 }
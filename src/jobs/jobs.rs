@@ -0,0 +1,431 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use hdrhistogram::Histogram;
+use tracing::{error, info, Instrument};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use crate::initiator::listener::listener::{Cell, Delivery, Interval, JobState, JobStatus, JobSummary, Latency, Measurement, SendRequest, Stats};
+use crate::histogram::histogram;
+use crate::metrics::metrics;
+use crate::store::store::Store;
+
+// Jobs tracks the jobs of an initiator by id
+#[derive(Debug, Clone, Default)]
+pub struct Jobs {
+    jobs: Arc<Mutex<HashMap<u32, JobStatus>>>,
+    // cancel tokens of running jobs
+    tokens: Arc<Mutex<HashMap<u32, CancelToken>>>,
+    // requests of running jobs, kept for the store
+    specs: Arc<Mutex<HashMap<u32, SendRequest>>>,
+    // finished jobs are recorded as jobs of initiator if there is a store
+    store: Option<Store>,
+    initiator: String,
+}
+
+impl Jobs {
+    pub fn with_store(store: Store, initiator: String) -> Jobs {
+        Jobs{
+            store: Some(store),
+            initiator,
+            ..Default::default()
+        }
+    }
+    // start registers a job and returns the token its data path stops on,
+    // ids of finished jobs can be reused
+    pub fn start(&self, request: &SendRequest) -> anyhow::Result<CancelToken> {
+        let id = request.id;
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.get(&id) {
+            if job.state == JobState::JobRunning as i32 {
+                return Err(anyhow::anyhow!("job {} is already running", id));
+            }
+            metrics::add(metrics::JOBS, &[("state", state_label(job.state))], -1.0);
+        }
+        metrics::inc(metrics::JOBS, &[("state", state_label(JobState::JobRunning as i32))]);
+        jobs.insert(id, JobStatus{
+            id,
+            state: JobState::JobRunning.into(),
+            ..Default::default()
+        });
+        if self.store.is_some() {
+            self.specs.lock().unwrap().insert(id, request.clone());
+        }
+        let token = CancelToken::default();
+        self.tokens.lock().unwrap().insert(id, token.clone());
+        Ok(token)
+    }
+    pub fn add_measurement(&self, id: u32, measurement: Measurement) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            job.measurements.push(measurement);
+        }
+    }
+    pub fn add_cell(&self, id: u32, cell: Cell) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            job.cells.push(cell);
+        }
+    }
+    // add_latency merges latency into the latency of the job
+    pub fn add_latency(&self, id: u32, latency: &Latency) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            job.latency = Some(merge_latencies(job.latency.iter().chain(Some(latency))));
+        }
+    }
+    // started records that the data path of a job started sending
+    pub fn started(&self, id: u32) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            job.started_unix_ns = unix_ns();
+        }
+    }
+    // sent records that the data path of a job sent its last message
+    pub fn sent(&self, id: u32) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            job.sent_unix_ns = unix_ns();
+        }
+    }
+    pub fn set_server_result(&self, id: u32, receive_wait: Latency, delivery: Delivery) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            job.server_receive_wait = Some(receive_wait);
+            job.delivery = Some(delivery);
+        }
+    }
+    pub fn add_interval(&self, id: u32, interval: Interval) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            job.intervals.push(interval);
+        }
+    }
+    pub fn complete(&self, id: u32) {
+        self.finish(id, JobState::JobCompleted, String::new());
+    }
+    pub fn fail(&self, id: u32, error: String) {
+        self.finish(id, JobState::JobFailed, error);
+    }
+    // cancel stops the data path of a running job, it stops posting
+    // messages and lets the ones in flight complete
+    pub fn cancel(&self, id: u32) -> anyhow::Result<JobStatus> {
+        match self.get(id) {
+            Some(job) if job.state == JobState::JobRunning as i32 => (),
+            Some(_) => return Err(anyhow::anyhow!("job {} is not running", id)),
+            None => return Err(anyhow::anyhow!("job {} not found", id)),
+        }
+        if let Some(token) = self.tokens.lock().unwrap().get(&id) {
+            token.cancel();
+        }
+        self.finish(id, JobState::JobCancelled, "cancelled".to_string());
+        Ok(self.get(id).unwrap_or_default())
+    }
+    // finish moves a running job to a final state, jobs which already
+    // finished, e.g. by being cancelled, keep their state
+    fn finish(&self, id: u32, state: JobState, error: String) {
+        let finished = match self.jobs.lock().unwrap().get_mut(&id) {
+            Some(job) => {
+                if job.state != JobState::JobRunning as i32 {
+                    return;
+                }
+                transition(job.state, state);
+                job.state = state.into();
+                job.error = error;
+                Some(job.clone())
+            },
+            None => None,
+        };
+        self.tokens.lock().unwrap().remove(&id);
+        let spec = self.specs.lock().unwrap().remove(&id);
+        if let (Some(store), Some(spec), Some(job)) = (&self.store, spec, finished) {
+            if let Err(e) = store.record(&self.initiator, &spec, &job) {
+                error!("failed to store the results of job {}: {}", id, e);
+                metrics::inc(metrics::ERRORS, &[("kind", "store")]);
+            }
+        }
+    }
+    pub fn get(&self, id: u32) -> Option<JobStatus> {
+        self.jobs.lock().unwrap().get(&id).cloned()
+    }
+    pub fn list(&self) -> Vec<JobSummary> {
+        let mut jobs = self.jobs.lock().unwrap().values().map(|job| JobSummary{
+            id: job.id,
+            state: job.state,
+            error: job.error.clone(),
+        }).collect::<Vec<_>>();
+        jobs.sort_by_key(|job| job.id);
+        jobs
+    }
+    pub fn running(&self) -> usize {
+        self.jobs.lock().unwrap().values().filter(|job| job.state == JobState::JobRunning as i32).count()
+    }
+}
+
+fn unix_ns() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64
+}
+
+// CancelToken tells a data path to stop at the next message boundary
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+}
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.notify.notify_waiters();
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+    // cancelled returns once the token is cancelled
+    pub async fn cancelled(&self) {
+        let notified = self.notify.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+        if self.is_cancelled() {
+            return;
+        }
+        notified.await;
+    }
+}
+
+fn state_label(state: i32) -> &'static str {
+    match JobState::try_from(state) {
+        Ok(JobState::JobRunning) => "running",
+        Ok(JobState::JobCompleted) => "completed",
+        Ok(JobState::JobFailed) => "failed",
+        Ok(JobState::JobCancelled) => "cancelled",
+        Err(_) => "unknown",
+    }
+}
+
+// transition moves a job between the state gauges
+fn transition(from: i32, to: JobState) {
+    metrics::add(metrics::JOBS, &[("state", state_label(from))], -1.0);
+    metrics::inc(metrics::JOBS, &[("state", state_label(to as i32))]);
+}
+
+// Progress counts the completed messages of a running job
+#[derive(Debug, Default)]
+pub struct Progress {
+    messages: AtomicU64,
+    bytes: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl Progress {
+    pub fn message(&self, bytes: u64) {
+        self.messages.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+    pub fn error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+    fn load(&self) -> (u64, u64, u64) {
+        (self.messages.load(Ordering::Relaxed), self.bytes.load(Ordering::Relaxed), self.errors.load(Ordering::Relaxed))
+    }
+}
+
+// IntervalReporter adds the progress of a job made in every interval to the
+// job until it is finished, like the interval reports of iperf
+pub struct IntervalReporter {
+    stop: Arc<Notify>,
+    handle: JoinHandle<()>,
+}
+
+impl IntervalReporter {
+    pub fn start(jobs: Jobs, id: u32, progress: Arc<Progress>, period: Duration) -> IntervalReporter {
+        let stop = Arc::new(Notify::new());
+        let stopped = stop.clone();
+        let handle = tokio::spawn(async move{
+            let start = Instant::now();
+            let mut ticker = tokio::time::interval_at(start + period, period);
+            let mut last = (start, (0, 0, 0));
+            loop {
+                let done = tokio::select! {
+                    _ = ticker.tick() => false,
+                    _ = stopped.notified() => true,
+                };
+                let now = Instant::now();
+                let counters = progress.load();
+                let interval = interval(last.0 - start, now - last.0, counters.0 - last.1.0, counters.1 - last.1.1, counters.2 - last.1.2);
+                // the last interval is partial and only reported when it saw traffic
+                if !done || interval.messages > 0 || interval.errors > 0 {
+                    info!("job {} [{:.1}-{:.1} s] {:.3} Gb/s {:.0} msg/s {} errors", id,
+                        interval.start_ns as f64 / 1e9, (interval.start_ns + interval.elapsed_ns) as f64 / 1e9,
+                        interval.bandwidth_gbps, interval.message_rate, interval.errors);
+                    jobs.add_interval(id, interval);
+                }
+                if done {
+                    break;
+                }
+                last = (now, counters);
+            }
+        }.in_current_span());
+        IntervalReporter{
+            stop,
+            handle,
+        }
+    }
+    // finish reports the last partial interval and stops the reporter
+    pub async fn finish(mut self) {
+        self.stop.notify_one();
+        let _ = (&mut self.handle).await;
+    }
+}
+
+// a reporter dropped with a cancelled job stops without a last interval
+impl Drop for IntervalReporter {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+fn interval(start: Duration, elapsed: Duration, messages: u64, bytes: u64, errors: u64) -> Interval {
+    let secs = elapsed.as_secs_f64();
+    Interval{
+        start_ns: start.as_nanos() as u64,
+        elapsed_ns: elapsed.as_nanos() as u64,
+        messages,
+        bytes,
+        bandwidth_gbps: if secs > 0.0 { bytes as f64 * 8.0 / secs / 1e9 } else { 0.0 },
+        message_rate: if secs > 0.0 { messages as f64 / secs } else { 0.0 },
+        errors,
+    }
+}
+
+pub fn measurement(message_size: u32, messages: u32, elapsed: Duration) -> Measurement {
+    let bytes = message_size as u64 * messages as u64;
+    let secs = elapsed.as_secs_f64();
+    Measurement{
+        message_size,
+        messages,
+        bytes,
+        elapsed_ns: elapsed.as_nanos() as u64,
+        bandwidth_gbps: if secs > 0.0 { bytes as f64 * 8.0 / secs / 1e9 } else { 0.0 },
+        time_per_message_us: if messages > 0 { secs * 1e6 / messages as f64 } else { 0.0 },
+        latency: None,
+    }
+}
+
+// cell summarizes the trials of a matrix cell
+pub fn cell(mtu: i32, message_size: u32, tx_depth: u32, connections: u32, trials: Vec<Measurement>) -> Cell {
+    let bandwidth = trials.iter().map(|m| m.bandwidth_gbps).collect::<Vec<_>>();
+    let time_per_message = trials.iter().map(|m| m.time_per_message_us).collect::<Vec<_>>();
+    Cell{
+        mtu,
+        message_size,
+        tx_depth,
+        connections,
+        bandwidth_gbps: Some(stats(&bandwidth)),
+        time_per_message_us: Some(stats(&time_per_message)),
+        latency: Some(merge_latencies(trials.iter().filter_map(|m| m.latency.as_ref()))),
+        trials,
+    }
+}
+
+// two-sided 95% quantiles of the t distribution for 1 to 30 degrees of freedom
+const T95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228,
+    2.201, 2.179, 2.160, 2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086,
+    2.080, 2.074, 2.069, 2.064, 2.060, 2.056, 2.052, 2.048, 2.045, 2.042,
+];
+
+// stats returns the mean, the sample standard deviation, the range and the
+// 95% confidence interval of the mean of values
+pub fn stats(values: &[f64]) -> Stats {
+    if values.is_empty() {
+        return Stats::default();
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let stddev = if values.len() > 1 {
+        (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+    } else {
+        0.0
+    };
+    let t = T95.get(values.len().saturating_sub(2)).copied().unwrap_or(1.960);
+    Stats{
+        mean,
+        stddev,
+        min: values.iter().copied().fold(f64::INFINITY, f64::min),
+        max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        ci95: if values.len() > 1 { t * stddev / n.sqrt() } else { 0.0 },
+    }
+}
+
+// total sums trials into one measurement
+pub fn total(message_size: u32, trials: &[Measurement]) -> Measurement {
+    let messages = trials.iter().map(|m| m.messages).sum();
+    let elapsed_ns = trials.iter().map(|m| m.elapsed_ns).sum();
+    Measurement{
+        latency: Some(merge_latencies(trials.iter().filter_map(|m| m.latency.as_ref()))),
+        ..measurement(message_size, messages, Duration::from_nanos(elapsed_ns))
+    }
+}
+
+// latency encodes a histogram of nanosecond latencies with its percentiles
+pub fn latency(histogram: &Histogram<u64>) -> Latency {
+    let us = |ns: u64| ns as f64 / 1e3;
+    Latency{
+        histogram: histogram::encode(histogram).unwrap_or_else(|e| {
+            error!("failed to encode latency histogram: {}", e);
+            Vec::new()
+        }),
+        count: histogram.len(),
+        p50_us: us(histogram.value_at_quantile(0.5)),
+        p99_us: us(histogram.value_at_quantile(0.99)),
+        p999_us: us(histogram.value_at_quantile(0.999)),
+        max_us: us(histogram.max()),
+    }
+}
+
+// merge_latencies combines the histograms of latencies
+pub fn merge_latencies<'a>(latencies: impl IntoIterator<Item = &'a Latency>) -> Latency {
+    match histogram::merge(latencies.into_iter().map(|l| l.histogram.as_slice())) {
+        Ok(histogram) => latency(&histogram),
+        Err(e) => {
+            error!("failed to merge latency histograms: {}", e);
+            Latency::default()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn stats_of_no_values_are_zero() {
+        let stats = stats(&[]);
+        assert_eq!((stats.mean, stats.stddev, stats.ci95), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn one_value_has_no_spread() {
+        let stats = stats(&[5.0]);
+        assert_eq!((stats.mean, stats.stddev, stats.min, stats.max, stats.ci95), (5.0, 0.0, 5.0, 5.0, 0.0));
+    }
+
+    #[test]
+    fn two_values_use_one_degree_of_freedom() {
+        let stats = stats(&[1.0, 3.0]);
+        assert!(close(stats.mean, 2.0));
+        assert!(close(stats.stddev, 2.0_f64.sqrt()));
+        assert!(close(stats.ci95, 12.706));
+        assert_eq!((stats.min, stats.max), (1.0, 3.0));
+    }
+
+    #[test]
+    fn many_values_use_the_normal_quantile() {
+        let values = (0..40).map(|v| (v % 2) as f64).collect::<Vec<_>>();
+        let stats = stats(&values);
+        assert!(close(stats.ci95, 1.960 * stats.stddev / 40.0_f64.sqrt()));
+        let values = (0..31).map(|v| (v % 2) as f64).collect::<Vec<_>>();
+        let stats = super::stats(&values);
+        assert!(close(stats.ci95, 2.042 * stats.stddev / 31.0_f64.sqrt()));
+    }
+}
@@ -0,0 +1 @@
+pub mod jobs;
\ No newline at end of file
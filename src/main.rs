@@ -1,28 +1,129 @@
+use std::path::PathBuf;
 use clap::Parser;
-use log::info;
+use tracing::info;
 pub mod server;
 pub mod initiator;
 pub mod queue;
+pub mod capabilities;
+pub mod rdma;
+pub mod pool;
+pub mod jobs;
+pub mod histogram;
+pub mod metrics;
+pub mod telemetry;
+pub mod controller;
+pub mod agent;
+pub mod store;
+pub mod protocol;
 
 #[derive(Parser, Debug)]
 struct Args{
     #[arg(short, long)]
     address: String,
+    // address the controller and other agents reach this host at, the bind
+    // address if unset
+    #[arg(long)]
+    advertise_address: Option<String>,
     #[arg(short, long)]
     server_port: u16,
     #[arg(short, long)]
     initiator_port: u16,
+    #[arg(long)]
+    device: Option<String>,
+    #[arg(long)]
+    ib_port: Option<u32>,
+    #[arg(long)]
+    gid_index: Option<u32>,
+    #[arg(long)]
+    mr_pool_size: Option<usize>,
+    #[arg(long)]
+    mr_alignment: Option<usize>,
+    #[arg(long)]
+    mr_align_2mib: bool,
+    #[arg(long)]
+    metrics_port: Option<u16>,
+    #[arg(long)]
+    otlp_endpoint: Option<String>,
+    // host:port of a controller to register with
+    #[arg(long)]
+    controller: Option<String>,
+    // run a controller on this port next to the server and initiator
+    #[arg(long)]
+    controller_port: Option<u16>,
+    // sqlite database the initiator keeps its finished jobs in
+    #[arg(long)]
+    results_db: Option<PathBuf>,
+    // sqlite database the controller keeps the jobs it collected in
+    #[arg(long)]
+    controller_results_db: Option<PathBuf>,
+}
+
+fn open_store(path: Option<PathBuf>) -> anyhow::Result<Option<store::store::Store>> {
+    path.map(|path| store::store::Store::open(&path).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))).transpose()
 }
 
 #[tokio::main]
 async fn main() {
-    env_logger::init();
     let args = Args::parse();
+    if let Err(e) = telemetry::telemetry::init(args.otlp_endpoint.clone()) {
+        eprintln!("telemetry error: {}", e);
+        return;
+    }
     let address = args.address;
+    let advertise_address = args.advertise_address.unwrap_or_else(|| address.clone());
+    if args.controller.is_some() && advertise_address.parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_unspecified()) {
+        eprintln!("{} can't be reached by other agents, set --advertise-address", advertise_address);
+        return;
+    }
     let initiator_address = format!("{}:{}", address, args.initiator_port);
-    let server = server::server::Server::new(address, args.server_port);
+    let device = match rdma::rdma::DeviceConfig::new(args.device, args.ib_port, args.gid_index) {
+        Ok(device) => device,
+        Err(e) => {
+            eprintln!("device error: {}", e);
+            return;
+        }
+    };
+    let mut pool = pool::pool::PoolConfig::default();
+    if let Some(mr_pool_size) = args.mr_pool_size {
+        pool.max_bytes = mr_pool_size;
+    }
+    if let Some(mr_alignment) = args.mr_alignment {
+        if !mr_alignment.is_power_of_two() {
+            eprintln!("mr alignment {} is not a power of two", mr_alignment);
+            return;
+        }
+        pool.alignment = mr_alignment;
+    }
+    pool.align_2mib = args.mr_align_2mib;
+    if let Some(metrics_port) = args.metrics_port {
+        let metrics_address = format!("{}:{}", address, metrics_port);
+        tokio::spawn(async move{
+            if let Err(e) = metrics::metrics::serve(metrics_address).await {
+                eprintln!("metrics error: {}", e);
+            }
+        });
+    }
+    let (store, controller_store) = match (open_store(args.results_db), open_store(args.controller_results_db)) {
+        (Ok(store), Ok(controller_store)) => (store, controller_store),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("results database error: {}", e);
+            return;
+        }
+    };
+    if let Some(controller_port) = args.controller_port {
+        let controller = controller::controller::Controller::new(format!("{}:{}", address, controller_port), controller_store);
+        tokio::spawn(async move{
+            if let Err(e) = controller.run().await {
+                eprintln!("controller error: {}", e);
+            }
+        });
+    }
+    let server = server::server::Server::new(address.clone(), args.server_port, device.clone());
     info!("initiator address: {}", initiator_address);
-    let mut initiator = initiator::initiator::Initiator::new(initiator_address);
+    let mut initiator = initiator::initiator::Initiator::new(initiator_address, format!("{}:{}", advertise_address, args.initiator_port), device, pool, store);
+    if let Some(controller) = args.controller {
+        tokio::spawn(agent::agent::run(controller, advertise_address, args.server_port, args.initiator_port, initiator.jobs()));
+    }
 
     let res = tokio::join!(
         server.run(),
@@ -33,6 +134,7 @@ async fn main() {
         (Err(e), _) => eprintln!("server error: {}", e),
         (_, Err(e)) => eprintln!("initiator error: {}", e),
     }
+    telemetry::telemetry::shutdown();
 
 
 }
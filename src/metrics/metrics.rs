@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use hyper::{Body, Request, Response, StatusCode};
+use hyper::header::CONTENT_TYPE;
+use hyper::service::{make_service_fn, service_fn};
+use tracing::info;
+use crate::pool::pool;
+
+pub const JOBS: &str = "rocky_jobs";
+pub const ACTIVE_LISTENERS: &str = "rocky_active_listeners";
+pub const ERRORS: &str = "rocky_errors_total";
+pub const QUEUE_DEPTH: &str = "rocky_queue_depth";
+pub const GRPC_REQUESTS: &str = "rocky_grpc_requests_total";
+pub const GRPC_REQUEST_DURATION: &str = "rocky_grpc_request_duration_seconds";
+
+// name, type and help of the labeled metrics, in the order they are exposed
+const METRICS: [(&str, &str, &str); 6] = [
+    (JOBS, "gauge", "Jobs of the initiator by state."),
+    (ACTIVE_LISTENERS, "gauge", "Server connections which are set up or receiving."),
+    (ERRORS, "counter", "Errors by kind."),
+    (QUEUE_DEPTH, "gauge", "Requests waiting in the queue."),
+    (GRPC_REQUESTS, "counter", "gRPC requests by method."),
+    (GRPC_REQUEST_DURATION, "histogram", "gRPC request latencies by method."),
+];
+
+// upper bounds of the gRPC latency buckets in seconds
+const BUCKETS: [f64; 10] = [0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 1.0];
+
+// the data path counts every message, so its counters are kept lock free
+static SENT_MESSAGES: AtomicU64 = AtomicU64::new(0);
+static SENT_BYTES: AtomicU64 = AtomicU64::new(0);
+static RECEIVED_MESSAGES: AtomicU64 = AtomicU64::new(0);
+static RECEIVED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+// values and histograms are keyed by metric name and rendered label set
+static VALUES: Mutex<BTreeMap<(&str, String), f64>> = Mutex::new(BTreeMap::new());
+static HISTOGRAMS: Mutex<BTreeMap<(&str, String), Histogram>> = Mutex::new(BTreeMap::new());
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+fn labels(labels: &[(&str, &str)]) -> String {
+    labels.iter().map(|(k, v)| format!("{}=\"{}\"", k, v.replace('\\', "\\\\").replace('"', "\\\""))).collect::<Vec<_>>().join(",")
+}
+
+pub fn add(name: &'static str, label_values: &[(&str, &str)], value: f64) {
+    *VALUES.lock().unwrap().entry((name, labels(label_values))).or_default() += value;
+}
+
+pub fn inc(name: &'static str, label_values: &[(&str, &str)]) {
+    add(name, label_values, 1.0);
+}
+
+pub fn set(name: &'static str, label_values: &[(&str, &str)], value: f64) {
+    VALUES.lock().unwrap().insert((name, labels(label_values)), value);
+}
+
+pub fn observe(name: &'static str, label_values: &[(&str, &str)], value: Duration) {
+    let secs = value.as_secs_f64();
+    let mut histograms = HISTOGRAMS.lock().unwrap();
+    let histogram = histograms.entry((name, labels(label_values))).or_default();
+    for (bucket, bound) in histogram.buckets.iter_mut().zip(BUCKETS) {
+        if secs <= bound {
+            *bucket += 1;
+        }
+    }
+    histogram.sum += secs;
+    histogram.count += 1;
+}
+
+pub fn sent(bytes: u64) {
+    SENT_MESSAGES.fetch_add(1, Ordering::Relaxed);
+    SENT_BYTES.fetch_add(bytes, Ordering::Relaxed);
+}
+
+pub fn received(bytes: u64) {
+    RECEIVED_MESSAGES.fetch_add(1, Ordering::Relaxed);
+    RECEIVED_BYTES.fetch_add(bytes, Ordering::Relaxed);
+}
+
+// GrpcTimer counts a gRPC request and records its latency when dropped
+pub struct GrpcTimer {
+    method: &'static str,
+    start: Instant,
+}
+
+pub fn grpc(method: &'static str) -> GrpcTimer {
+    GrpcTimer{
+        method,
+        start: Instant::now(),
+    }
+}
+
+impl Drop for GrpcTimer {
+    fn drop(&mut self) {
+        inc(GRPC_REQUESTS, &[("method", self.method)]);
+        observe(GRPC_REQUEST_DURATION, &[("method", self.method)], self.start.elapsed());
+    }
+}
+
+fn series(name: &str, labels: &str) -> String {
+    if labels.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}{{{}}}", name, labels)
+    }
+}
+
+// render returns all metrics in the Prometheus text format
+pub fn render() -> String {
+    let mut out = String::new();
+    let counters = [
+        ("rocky_sent_messages_total", "Messages sent by the initiator.", SENT_MESSAGES.load(Ordering::Relaxed)),
+        ("rocky_sent_bytes_total", "Bytes sent by the initiator.", SENT_BYTES.load(Ordering::Relaxed)),
+        ("rocky_received_messages_total", "Messages received by the server.", RECEIVED_MESSAGES.load(Ordering::Relaxed)),
+        ("rocky_received_bytes_total", "Bytes received by the server.", RECEIVED_BYTES.load(Ordering::Relaxed)),
+    ];
+    let stats = pool::stats();
+    let pool_counters = [
+        ("rocky_mr_registrations_total", "Memory regions registered by the send buffer pools.", stats.registrations),
+        ("rocky_mr_reuses_total", "Memory regions reused from the send buffer pools.", stats.reuses),
+        ("rocky_mr_evictions_total", "Memory regions deregistered by full send buffer pools.", stats.evictions),
+    ];
+    for (name, help, value) in counters.iter().chain(pool_counters.iter()) {
+        let _ = writeln!(out, "# HELP {} {}\n# TYPE {} counter\n{} {}", name, help, name, name, value);
+    }
+    let values = VALUES.lock().unwrap();
+    let histograms = HISTOGRAMS.lock().unwrap();
+    for (name, kind, help) in METRICS {
+        let _ = writeln!(out, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+        for ((_, labels), value) in values.range((name, String::new())..).take_while(|((n, _), _)| *n == name) {
+            let _ = writeln!(out, "{} {}", series(name, labels), value);
+        }
+        for ((_, labels), histogram) in histograms.range((name, String::new())..).take_while(|((n, _), _)| *n == name) {
+            let sep = if labels.is_empty() { "" } else { "," };
+            for (bound, count) in BUCKETS.iter().zip(histogram.buckets) {
+                let _ = writeln!(out, "{}_bucket{{{}{}le=\"{}\"}} {}", name, labels, sep, bound, count);
+            }
+            let _ = writeln!(out, "{}_bucket{{{}{}le=\"+Inf\"}} {}", name, labels, sep, histogram.count);
+            let _ = writeln!(out, "{} {}", series(&format!("{}_sum", name), labels), histogram.sum);
+            let _ = writeln!(out, "{} {}", series(&format!("{}_count", name), labels), histogram.count);
+        }
+    }
+    out
+}
+
+async fn handle(request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let response = if request.uri().path() == "/metrics" {
+        Response::builder()
+            .header(CONTENT_TYPE, "text/plain; version=0.0.4")
+            .body(Body::from(render()))
+    } else {
+        Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+    };
+    Ok(response.unwrap())
+}
+
+// serve exposes the metrics at /metrics over http
+pub async fn serve(address: String) -> anyhow::Result<()> {
+    let addr = address.parse()?;
+    info!("serving metrics at http://{}/metrics", address);
+    let service = make_service_fn(|_| async { Ok::<_, Infallible>(service_fn(handle)) });
+    hyper::Server::try_bind(&addr)?.serve(service).await?;
+    Ok(())
+}
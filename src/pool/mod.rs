@@ -0,0 +1 @@
+pub mod pool;
\ No newline at end of file
@@ -0,0 +1,181 @@
+use std::alloc::Layout;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+use async_rdma::{LocalMr, LocalMrReadAccess, Rdma, RdmaBuilder};
+use crate::rdma::rdma::{DeviceConfig, QpConfig};
+
+// async-rdma allocates the regions itself, so the pool can only align and
+// round them to 2 MiB, which lets transparent huge pages back them
+const ALIGN_2MIB: usize = 2 << 20;
+// how many domains are kept, the least recently used one is dropped beyond
+const MAX_DOMAINS: usize = 8;
+
+static REGISTRATIONS: AtomicU64 = AtomicU64::new(0);
+static REUSES: AtomicU64 = AtomicU64::new(0);
+static EVICTIONS: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone)]
+pub struct PoolConfig {
+    // upper bound of the bytes kept idle in a pool
+    pub max_bytes: usize,
+    pub alignment: usize,
+    // align and round regions to 2 MiB
+    pub align_2mib: bool,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        PoolConfig{
+            max_bytes: 64 << 20,
+            alignment: 64,
+            align_2mib: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PoolStats {
+    pub registrations: u64,
+    pub reuses: u64,
+    pub evictions: u64,
+}
+
+// stats returns the counters of all pools in the process
+pub fn stats() -> PoolStats {
+    PoolStats{
+        registrations: REGISTRATIONS.load(Ordering::Relaxed),
+        reuses: REUSES.load(Ordering::Relaxed),
+        evictions: EVICTIONS.load(Ordering::Relaxed),
+    }
+}
+
+// MrPool keeps registered memory regions for reuse. async-rdma registers
+// regions against the protection domain of the Rdma that allocated them,
+// so a pool is only shared by connections of the same protection domain
+pub struct MrPool {
+    config: PoolConfig,
+    free: Mutex<HashMap<usize, Vec<LocalMr>>>,
+    idle_bytes: Mutex<usize>,
+}
+
+impl MrPool {
+    pub fn new(config: PoolConfig) -> MrPool {
+        MrPool{
+            config,
+            free: Mutex::new(HashMap::new()),
+            idle_bytes: Mutex::new(0),
+        }
+    }
+    fn layout(&self, size: usize, alignment: usize) -> anyhow::Result<Layout> {
+        let (size, alignment) = if self.config.align_2mib {
+            (size.div_ceil(ALIGN_2MIB) * ALIGN_2MIB, ALIGN_2MIB.max(alignment))
+        } else {
+            (size, alignment)
+        };
+        Ok(Layout::from_size_align(size, alignment)?)
+    }
+    // take returns an idle region of the pooled size for size bytes
+    // or registers a new one
+    pub fn take(&self, rdma: &Rdma, size: usize) -> anyhow::Result<LocalMr> {
+        self.take_aligned(rdma, size, self.config.alignment)
+    }
+    // take_aligned is take with an alignment other than the pool's
+    pub fn take_aligned(&self, rdma: &Rdma, size: usize, alignment: usize) -> anyhow::Result<LocalMr> {
+        let layout = self.layout(size, alignment)?;
+        let lmr = self.free.lock().unwrap().get_mut(&layout.size()).and_then(|free| {
+            let i = free.iter().position(|lmr| lmr.addr() % layout.align() == 0)?;
+            Some(free.swap_remove(i))
+        });
+        if let Some(lmr) = lmr {
+            *self.idle_bytes.lock().unwrap() -= layout.size();
+            REUSES.fetch_add(1, Ordering::Relaxed);
+            return Ok(lmr);
+        }
+        let lmr = rdma.alloc_local_mr(layout)?;
+        REGISTRATIONS.fetch_add(1, Ordering::Relaxed);
+        Ok(lmr)
+    }
+    // put hands a region back, it is deregistered when the pool is full
+    pub fn put(&self, lmr: LocalMr) {
+        let size = lmr.length();
+        let mut idle_bytes = self.idle_bytes.lock().unwrap();
+        if *idle_bytes + size > self.config.max_bytes {
+            EVICTIONS.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        *idle_bytes += size;
+        self.free.lock().unwrap().entry(size).or_default().push(lmr);
+    }
+}
+
+// DomainKey is everything the parent Rdma of a domain is built with,
+// connections made from the parent inherit its queue pair settings
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DomainKey {
+    pub device: DeviceConfig,
+    pub qp: QpConfig,
+    pub mtu: u32,
+    pub max_message_length: usize,
+}
+
+// Domain is a parent Rdma that is never connected itself and the pool of
+// the regions registered against its protection domain
+pub struct Domain {
+    pub rdma: Rdma,
+    pub pool: Arc<MrPool>,
+    last_used: Mutex<Instant>,
+}
+
+// Domains keeps a domain per rdma configuration across jobs, so repeated
+// jobs reuse the regions registered by earlier ones
+#[derive(Clone, Default)]
+pub struct Domains {
+    config: PoolConfig,
+    domains: Arc<Mutex<HashMap<DomainKey, Arc<Domain>>>>,
+}
+
+impl fmt::Debug for Domains {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Domains").field("config", &self.config).finish()
+    }
+}
+
+impl Domains {
+    pub fn new(config: PoolConfig) -> Domains {
+        Domains{
+            config,
+            domains: Arc::default(),
+        }
+    }
+    // get returns the domain of key, building its parent with builder
+    // if there is none
+    pub fn get(&self, key: DomainKey, builder: RdmaBuilder) -> anyhow::Result<Arc<Domain>> {
+        let mut domains = self.domains.lock().unwrap();
+        if let Some(domain) = domains.get(&key) {
+            *domain.last_used.lock().unwrap() = Instant::now();
+            return Ok(domain.clone());
+        }
+        if domains.len() >= MAX_DOMAINS {
+            let oldest = domains.iter().
+                min_by_key(|(_, domain)| *domain.last_used.lock().unwrap()).
+                map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                domains.remove(&oldest);
+            }
+        }
+        let domain = Arc::new(Domain{
+            rdma: builder.build()?,
+            pool: Arc::new(self.pool()),
+            last_used: Mutex::new(Instant::now()),
+        });
+        domains.insert(key, domain.clone());
+        Ok(domain)
+    }
+    // pool returns a pool of its own for a connection outside a domain
+    pub fn pool(&self) -> MrPool {
+        MrPool::new(self.config.clone())
+    }
+}
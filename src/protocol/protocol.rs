@@ -0,0 +1,50 @@
+use std::time::Duration;
+use crate::initiator::listener::listener;
+use crate::server::connection_manager::connection_manager::{ConnectionType, ImmMode, Operation};
+
+// version of the JobSpec the initiator sends, servers ignore the fields of
+// newer versions they don't know
+pub const JOB_SPEC_VERSION: u32 = 2;
+// how long a server waits for the next message of a connection unless the
+// job sets it
+pub const DEFAULT_RECEIVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// immediate returns the immediate data carried by the seq-th message with
+// immediate of a job. The initiator sends it and the server checks it
+pub fn immediate(imm_mode: ImmMode, imm_data: u32, id: u32, seq: u32) -> u32 {
+    match imm_mode {
+        ImmMode::ImmConstant => imm_data,
+        ImmMode::ImmSequence => imm_data.wrapping_add(seq),
+        ImmMode::ImmJobId => id,
+    }
+}
+
+impl From<listener::ImmMode> for ImmMode {
+    fn from(imm_mode: listener::ImmMode) -> Self {
+        match imm_mode {
+            listener::ImmMode::ImmConstant => ImmMode::ImmConstant,
+            listener::ImmMode::ImmSequence => ImmMode::ImmSequence,
+            listener::ImmMode::ImmJobId => ImmMode::ImmJobId,
+        }
+    }
+}
+
+impl From<listener::Operation> for Operation {
+    fn from(op: listener::Operation) -> Self {
+        match op {
+            listener::Operation::Send => Operation::Send,
+            listener::Operation::SendWithImm => Operation::SendWithImm,
+            listener::Operation::SendMixed => Operation::SendMixed,
+        }
+    }
+}
+
+impl From<listener::ConnectionType> for ConnectionType {
+    fn from(connection_type: listener::ConnectionType) -> Self {
+        match connection_type {
+            listener::ConnectionType::RcTcp => ConnectionType::RcTcp,
+            listener::ConnectionType::RcCm => ConnectionType::RcCm,
+            listener::ConnectionType::RcGrpc => ConnectionType::RcGrpc,
+        }
+    }
+}
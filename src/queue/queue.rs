@@ -1,8 +1,12 @@
 use std::collections::VecDeque;
 
-use log::info;
+use tracing::info;
 
 use crate::initiator::{initiator, listener::listener::SendRequest};
+use crate::rdma::rdma::DeviceConfig;
+use crate::pool::pool::Domains;
+use crate::jobs::jobs::Jobs;
+use crate::metrics::metrics;
 
 pub struct Queue{}
 
@@ -13,18 +17,21 @@ impl Queue {
     pub async fn run(&mut self, mut rx: tokio::sync::mpsc::Receiver<SendRequest>) -> anyhow::Result<()> {
         let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(10));
         let mut queue = VecDeque::new();
+        let domains = Domains::default();
         loop{
             tokio::select! {
                 _ = interval.tick() => {
                     if let Some(request) = queue.pop_front() {
+                        metrics::set(metrics::QUEUE_DEPTH, &[], queue.len() as f64);
                         info!("sending request: {:?}", request);
-                        initiator::initiate(request).await?;
+                        initiator::initiate(request, DeviceConfig::default(), domains.clone(), Jobs::default()).await?;
                     }
                 },
                 request = rx.recv() => {
                     if let Some(request) = request {
                         info!("received request: {:?}", request);
                         queue.push_back(request);
+                        metrics::set(metrics::QUEUE_DEPTH, &[], queue.len() as f64);
                     }
                 },
             }
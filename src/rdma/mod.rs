@@ -0,0 +1 @@
+pub mod rdma;
\ No newline at end of file
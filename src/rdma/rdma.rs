@@ -0,0 +1,360 @@
+use async_rdma::{QueuePairEndpoint, RdmaBuilder};
+use serde::{Deserialize, Serialize};
+use crate::server::connection_manager::connection_manager::QpEndpoint;
+
+// DeviceConfig selects the rdma device, physical port and gid index.
+// Unset fields leave the RdmaBuilder defaults in place
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DeviceConfig {
+    pub device: Option<String>,
+    pub port_num: Option<u8>,
+    pub gid_index: Option<usize>,
+}
+
+impl DeviceConfig {
+    pub fn new(device: Option<String>, port_num: Option<u32>, gid_index: Option<u32>) -> anyhow::Result<DeviceConfig> {
+        let port_num = match port_num {
+            Some(port_num) => Some(u8::try_from(port_num).map_err(|_| anyhow::anyhow!("invalid ib port {}", port_num))?),
+            None => None,
+        };
+        Ok(DeviceConfig{
+            device,
+            port_num,
+            gid_index: gid_index.map(|gid_index| gid_index as usize),
+        })
+    }
+    // merge returns the config with the set fields of overrides taking precedence
+    pub fn merge(&self, overrides: DeviceConfig) -> DeviceConfig {
+        DeviceConfig{
+            device: overrides.device.or_else(|| self.device.clone()),
+            port_num: overrides.port_num.or(self.port_num),
+            gid_index: overrides.gid_index.or(self.gid_index),
+        }
+    }
+    pub fn apply(&self, mut builder: RdmaBuilder) -> RdmaBuilder {
+        if let Some(device) = &self.device {
+            builder = builder.set_dev(device);
+        }
+        if let Some(port_num) = self.port_num {
+            builder = builder.set_port_num(port_num);
+        }
+        if let Some(gid_index) = self.gid_index {
+            builder = builder.set_gid_index(gid_index);
+        }
+        builder
+    }
+}
+
+// QpConfig holds the queue pair and completion queue knobs of a job,
+// the same values are applied on the initiator and the server
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct QpConfig {
+    pub max_send_wr: Option<u32>,
+    pub max_recv_wr: Option<u32>,
+    pub max_sge: Option<u32>,
+    pub cq_size: Option<u32>,
+    pub max_inline_data: Option<u32>,
+    pub retry_count: Option<u32>,
+    pub rnr_retry: Option<u32>,
+    pub timeout: Option<u32>,
+    pub min_rnr_timer: Option<u32>,
+}
+
+impl QpConfig {
+    // validate checks the values against the ranges of the ibverbs attributes
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let limits = [
+            ("retry_count", self.retry_count, 7),
+            ("rnr_retry", self.rnr_retry, 7),
+            ("timeout", self.timeout, 31),
+            ("min_rnr_timer", self.min_rnr_timer, 31),
+        ];
+        for (name, value, max) in limits {
+            if let Some(value) = value {
+                if value > max {
+                    return Err(anyhow::anyhow!("{} {} out of range 0..={}", name, value, max));
+                }
+            }
+        }
+        let non_zero = [
+            ("max_send_wr", self.max_send_wr),
+            ("max_recv_wr", self.max_recv_wr),
+            ("max_sge", self.max_sge),
+            ("cq_size", self.cq_size),
+        ];
+        for (name, value) in non_zero {
+            if value == Some(0) {
+                return Err(anyhow::anyhow!("{} must be greater than 0", name));
+            }
+        }
+        Ok(())
+    }
+    // apply expects a validated config
+    pub fn apply(&self, mut builder: RdmaBuilder) -> RdmaBuilder {
+        if let Some(max_send_wr) = self.max_send_wr {
+            builder = builder.set_qp_max_send_wr(max_send_wr);
+        }
+        if let Some(max_recv_wr) = self.max_recv_wr {
+            builder = builder.set_qp_max_recv_wr(max_recv_wr);
+        }
+        if let Some(max_sge) = self.max_sge {
+            builder = builder.set_qp_max_send_sge(max_sge).set_qp_max_recv_sge(max_sge);
+        }
+        if let Some(cq_size) = self.cq_size {
+            builder = builder.set_cq_size(cq_size);
+        }
+        if let Some(max_inline_data) = self.max_inline_data {
+            builder = builder.set_max_inline_data(max_inline_data);
+        }
+        if let Some(retry_count) = self.retry_count {
+            builder = builder.set_retry_cnt(retry_count as u8);
+        }
+        if let Some(rnr_retry) = self.rnr_retry {
+            builder = builder.set_rnr_retry(rnr_retry as u8);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.set_timeout(timeout as u8);
+        }
+        if let Some(min_rnr_timer) = self.min_rnr_timer {
+            builder = builder.set_min_rnr_timer(min_rnr_timer as u8);
+        }
+        builder
+    }
+}
+
+// QosConfig holds the traffic class, service level and flow label of the
+// address vector of a queue pair. async-rdma builds the address vector
+// itself and has no setters for them, so jobs setting any are rejected
+#[derive(Debug, Clone, Default)]
+pub struct QosConfig {
+    pub traffic_class: Option<u32>,
+    pub service_level: Option<u32>,
+    pub flow_label: Option<u32>,
+}
+
+impl QosConfig {
+    // new resolves dscp into the upper six bits of the traffic class,
+    // leaving the ecn bits cleared
+    pub fn new(traffic_class: Option<u32>, dscp: Option<u32>, service_level: Option<u32>, flow_label: Option<u32>) -> anyhow::Result<QosConfig> {
+        let traffic_class = match (traffic_class, dscp) {
+            (Some(_), Some(_)) => return Err(anyhow::anyhow!("traffic class and dscp are mutually exclusive")),
+            (Some(traffic_class), None) => Some(traffic_class),
+            (None, Some(dscp)) => {
+                if dscp > 63 {
+                    return Err(anyhow::anyhow!("dscp {} out of range 0..=63", dscp));
+                }
+                Some(dscp << 2)
+            },
+            (None, None) => None,
+        };
+        let qos = QosConfig{
+            traffic_class,
+            service_level,
+            flow_label,
+        };
+        qos.validate()?;
+        Ok(qos)
+    }
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let limits = [
+            ("traffic_class", self.traffic_class, 255),
+            ("service_level", self.service_level, 15),
+            ("flow_label", self.flow_label, 0xfffff),
+        ];
+        for (name, value, max) in limits {
+            if let Some(value) = value {
+                if value > max {
+                    return Err(anyhow::anyhow!("{} {} out of range 0..={}", name, value, max));
+                }
+            }
+        }
+        Ok(())
+    }
+    // supported fails for a config that sets anything
+    pub fn supported(&self) -> anyhow::Result<()> {
+        let set = [
+            ("traffic class", self.traffic_class),
+            ("service level", self.service_level),
+            ("flow label", self.flow_label),
+        ];
+        let set = set.iter().filter(|(_, value)| value.is_some()).map(|(name, _)| *name).collect::<Vec<_>>();
+        if !set.is_empty() {
+            return Err(anyhow::anyhow!("{} can't be set, async-rdma has no setters for the address vector", set.join(", ")));
+        }
+        Ok(())
+    }
+}
+
+// SgeLayout splits each message into scatter-gather elements, they lie
+// back to back in the message's region
+#[derive(Debug, Clone)]
+pub struct SgeLayout {
+    pub sizes: Vec<u32>,
+}
+
+impl SgeLayout {
+    // new uses sizes when given, otherwise splits message_size evenly into
+    // count elements with the remainder going to the last one.
+    // A single element means no layout
+    pub fn new(message_size: u32, count: u32, sizes: &[u32]) -> anyhow::Result<Option<SgeLayout>> {
+        if sizes.is_empty() && count <= 1 {
+            return Ok(None);
+        }
+        let sizes = if sizes.is_empty() {
+            if count > message_size {
+                return Err(anyhow::anyhow!("cannot split {} bytes into {} elements", message_size, count));
+            }
+            let mut sizes = vec![message_size / count; count as usize];
+            sizes[count as usize - 1] += message_size % count;
+            sizes
+        } else {
+            if count != 0 && count as usize != sizes.len() {
+                return Err(anyhow::anyhow!("sge count {} does not match {} sge sizes", count, sizes.len()));
+            }
+            if sizes.contains(&0) {
+                return Err(anyhow::anyhow!("sge sizes must be greater than 0"));
+            }
+            let total: u64 = sizes.iter().map(|size| *size as u64).sum();
+            if total != message_size as u64 {
+                return Err(anyhow::anyhow!("sge sizes add up to {} bytes, message size is {}", total, message_size));
+            }
+            sizes.to_vec()
+        };
+        Ok(Some(SgeLayout{
+            sizes,
+        }))
+    }
+    pub fn count(&self) -> u32 {
+        self.sizes.len() as u32
+    }
+}
+
+// async-rdma's qp_handshake starts the send and receive queues at psn 0
+// and has no setter for it, so this is the psn both sides send and expect
+const START_PSN: u32 = 0;
+
+// Endpoint mirrors how async-rdma serializes a QueuePairEndpoint, whose
+// fields are private. It sends endpoints with bincode in its own handshake
+#[derive(Serialize, Deserialize)]
+struct Endpoint {
+    qp_num: u32,
+    lid: u16,
+    gid: [u8; 16],
+}
+
+// the bincode length of Endpoint, an endpoint of another length has a
+// layout the mirror doesn't match
+const ENDPOINT_LEN: usize = 22;
+
+impl TryFrom<QueuePairEndpoint> for QpEndpoint {
+    type Error = anyhow::Error;
+    fn try_from(endpoint: QueuePairEndpoint) -> Result<Self, Self::Error> {
+        let encoded = bincode::serialize(&endpoint)?;
+        if encoded.len() != ENDPOINT_LEN {
+            return Err(anyhow::anyhow!("unexpected qp endpoint encoding of {} bytes", encoded.len()));
+        }
+        let endpoint: Endpoint = bincode::deserialize(&encoded)?;
+        Ok(QpEndpoint{
+            qp_num: endpoint.qp_num,
+            lid: endpoint.lid as u32,
+            gid: endpoint.gid.to_vec(),
+            psn: START_PSN,
+        })
+    }
+}
+
+impl TryFrom<QpEndpoint> for QueuePairEndpoint {
+    type Error = anyhow::Error;
+    fn try_from(endpoint: QpEndpoint) -> Result<Self, Self::Error> {
+        if endpoint.psn != START_PSN {
+            return Err(anyhow::anyhow!("peer starts at psn {}, the local queue pair expects {}", endpoint.psn, START_PSN));
+        }
+        let endpoint = Endpoint{
+            qp_num: endpoint.qp_num,
+            lid: u16::try_from(endpoint.lid).map_err(|_| anyhow::anyhow!("invalid lid {}", endpoint.lid))?,
+            gid: endpoint.gid.as_slice().try_into().map_err(|_| anyhow::anyhow!("invalid gid length {}", endpoint.gid.len()))?,
+        };
+        Ok(bincode::deserialize(&bincode::serialize(&endpoint)?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sge_counts_split_evenly_with_the_remainder_last() {
+        assert_eq!(SgeLayout::new(10, 3, &[]).unwrap().unwrap().sizes, vec![3, 3, 4]);
+        assert_eq!(SgeLayout::new(12, 4, &[]).unwrap().unwrap().sizes, vec![3, 3, 3, 3]);
+        assert_eq!(SgeLayout::new(5, 5, &[]).unwrap().unwrap().sizes, vec![1, 1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn single_elements_have_no_layout() {
+        assert!(SgeLayout::new(10, 0, &[]).unwrap().is_none());
+        assert!(SgeLayout::new(10, 1, &[]).unwrap().is_none());
+    }
+
+    #[test]
+    fn invalid_sge_layouts_are_rejected() {
+        assert!(SgeLayout::new(2, 3, &[]).is_err());
+        assert!(SgeLayout::new(10, 3, &[5, 5]).is_err());
+        assert!(SgeLayout::new(10, 0, &[4, 4]).is_err());
+        assert!(SgeLayout::new(10, 0, &[10, 0]).is_err());
+        assert_eq!(SgeLayout::new(10, 2, &[4, 6]).unwrap().unwrap().sizes, vec![4, 6]);
+    }
+
+    #[test]
+    fn dscp_fills_the_upper_bits_of_the_traffic_class() {
+        assert_eq!(QosConfig::new(None, Some(63), None, None).unwrap().traffic_class, Some(252));
+        assert_eq!(QosConfig::new(None, Some(0), None, None).unwrap().traffic_class, Some(0));
+    }
+
+    #[test]
+    fn out_of_range_qos_values_are_rejected() {
+        assert!(QosConfig::new(None, Some(64), None, None).is_err());
+        assert!(QosConfig::new(Some(256), None, None, None).is_err());
+        assert!(QosConfig::new(None, None, Some(16), None).is_err());
+        assert!(QosConfig::new(None, None, Some(15), None).is_ok());
+        assert!(QosConfig::new(None, None, None, Some(0x100000)).is_err());
+        assert!(QosConfig::new(Some(1), Some(1), None, None).is_err());
+    }
+
+    #[test]
+    fn qos_settings_are_unsupported() {
+        assert!(QosConfig::default().supported().is_ok());
+        assert!(QosConfig::new(None, None, Some(3), None).unwrap().supported().is_err());
+    }
+
+    #[test]
+    fn out_of_range_qp_values_are_rejected() {
+        assert!(QpConfig{ retry_count: Some(8), ..Default::default() }.validate().is_err());
+        assert!(QpConfig{ timeout: Some(31), ..Default::default() }.validate().is_ok());
+        assert!(QpConfig{ max_sge: Some(0), ..Default::default() }.validate().is_err());
+    }
+
+    fn endpoint(psn: u32) -> QpEndpoint {
+        QpEndpoint{
+            qp_num: 17,
+            lid: 3,
+            gid: (0..16).collect(),
+            psn,
+        }
+    }
+
+    #[test]
+    fn endpoints_convert_both_ways() {
+        let remote = QueuePairEndpoint::try_from(endpoint(START_PSN)).unwrap();
+        assert_eq!(QpEndpoint::try_from(remote).unwrap(), endpoint(START_PSN));
+    }
+
+    #[test]
+    fn endpoints_with_other_psns_or_gids_are_rejected() {
+        assert!(QueuePairEndpoint::try_from(endpoint(5)).is_err());
+        let short = QpEndpoint{
+            gid: vec![0; 4],
+            ..endpoint(START_PSN)
+        };
+        assert!(QueuePairEndpoint::try_from(short).is_err());
+    }
+}
@@ -1,20 +1,277 @@
+/// fields 1-7 are kept for servers and initiators which predate JobSpec,
+/// servers prefer spec when it is set
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ConnectRequest {
+    #[deprecated]
     #[prost(uint32, tag = "1")]
     pub id: u32,
+    #[deprecated]
     #[prost(uint32, tag = "2")]
     pub messages: u32,
+    #[deprecated]
     #[prost(uint32, tag = "3")]
     pub message_size: u32,
+    #[deprecated]
     #[prost(uint32, tag = "4")]
     pub mtu: u32,
+    #[deprecated]
+    #[prost(enumeration = "ImmMode", tag = "5")]
+    pub imm_mode: i32,
+    #[deprecated]
+    #[prost(uint32, tag = "6")]
+    pub imm_data: u32,
+    #[deprecated]
+    #[prost(enumeration = "Operation", tag = "7")]
+    pub op: i32,
+    #[prost(message, optional, tag = "8")]
+    pub spec: ::core::option::Option<JobSpec>,
+    #[prost(message, optional, tag = "9")]
+    pub endpoint: ::core::option::Option<QpEndpoint>,
+}
+/// QpEndpoint is exchanged in Init for RC_GRPC connections
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct QpEndpoint {
+    #[prost(uint32, tag = "1")]
+    pub qp_num: u32,
+    #[prost(uint32, tag = "2")]
+    pub lid: u32,
+    #[prost(bytes = "vec", tag = "3")]
+    pub gid: ::prost::alloc::vec::Vec<u8>,
+    /// psn the send queue of the sender starts at, the receive queue of the
+    /// peer has to expect it
+    #[prost(uint32, tag = "4")]
+    pub psn: u32,
+}
+/// JobSpec mirrors listener.SendRequest
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JobSpec {
+    #[prost(uint32, tag = "1")]
+    pub version: u32,
+    #[prost(uint32, tag = "2")]
+    pub id: u32,
+    #[prost(enumeration = "Operation", tag = "3")]
+    pub op: i32,
+    #[prost(uint32, tag = "4")]
+    pub messages: u32,
+    #[prost(uint32, tag = "5")]
+    pub message_size: u32,
+    #[prost(uint32, tag = "6")]
+    pub mtu: u32,
+    #[prost(enumeration = "ImmMode", tag = "7")]
+    pub imm_mode: i32,
+    #[prost(uint32, tag = "8")]
+    pub imm_data: u32,
+    #[prost(string, optional, tag = "9")]
+    pub device: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(uint32, optional, tag = "10")]
+    pub ib_port: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "11")]
+    pub gid_index: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "12")]
+    pub max_send_wr: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "13")]
+    pub max_recv_wr: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "14")]
+    pub max_sge: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "15")]
+    pub cq_size: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "16")]
+    pub max_inline_data: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "17")]
+    pub retry_count: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "18")]
+    pub rnr_retry: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "19")]
+    pub timeout: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "20")]
+    pub min_rnr_timer: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "21")]
+    pub traffic_class: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "22")]
+    pub service_level: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "23")]
+    pub flow_label: ::core::option::Option<u32>,
+    #[prost(enumeration = "ConnectionType", tag = "24")]
+    pub connection_type: i32,
+    #[prost(uint32, tag = "27")]
+    pub tx_depth: u32,
+    /// the server stops waiting for the messages of a connection when none
+    /// arrived for this long and reports the ones it received, 0 uses the
+    /// server default
+    #[prost(uint32, tag = "28")]
+    pub receive_timeout_ms: u32,
+    /// the server doesn't compare the immediates against the ones it expects
+    #[prost(bool, tag = "29")]
+    pub skip_integrity_check: bool,
+    /// the first message is not expected before this wall clock time in
+    /// nanoseconds since the unix epoch
+    #[prost(uint64, tag = "30")]
+    pub start_at_unix_ns: u64,
+    /// messages at the start of every connection left out of the receive waits
+    #[prost(uint32, tag = "31")]
+    pub warmup_messages: u32,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ConnectReply {
     #[prost(uint32, tag = "1")]
     pub port: u32,
+    #[prost(message, optional, tag = "2")]
+    pub endpoint: ::core::option::Option<QpEndpoint>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JobRequest {
+    #[prost(uint32, tag = "1")]
+    pub id: u32,
+}
+/// JobResult holds the receive wait times and counters of the finished
+/// connections of a job, it is removed from the server once all connections
+/// are finished
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JobResult {
+    #[prost(uint32, tag = "1")]
+    pub id: u32,
+    #[prost(uint32, tag = "2")]
+    pub active_connections: u32,
+    /// V2 deflate encoded HDR histogram of the time from posting each receive
+    /// after the warmup to its completion in nanoseconds, it includes the
+    /// wait for the initiator to send
+    #[prost(bytes = "vec", tag = "3")]
+    pub receive_wait_histogram: ::prost::alloc::vec::Vec<u8>,
+    #[prost(uint64, tag = "4")]
+    pub received: u64,
+    #[prost(uint64, tag = "5")]
+    pub receive_errors: u64,
+    /// messages with an unexpected immediate or of the wrong kind
+    #[prost(uint64, tag = "6")]
+    pub integrity_errors: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CapabilitiesRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CapabilitiesReply {
+    #[prost(string, tag = "1")]
+    pub version: ::prost::alloc::string::String,
+    #[prost(enumeration = "Operation", repeated, tag = "2")]
+    pub operations: ::prost::alloc::vec::Vec<i32>,
+    #[prost(string, repeated, tag = "3")]
+    pub transports: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(uint32, repeated, tag = "4")]
+    pub mtus: ::prost::alloc::vec::Vec<u32>,
+    #[prost(uint32, tag = "5")]
+    pub max_message_size: u32,
+    #[prost(message, repeated, tag = "6")]
+    pub devices: ::prost::alloc::vec::Vec<Device>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Device {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "2")]
+    pub port: u32,
+    #[prost(string, tag = "3")]
+    pub state: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub link_layer: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub rate: ::prost::alloc::string::String,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Operation {
+    Send = 0,
+    SendWithImm = 1,
+    SendMixed = 2,
+}
+impl Operation {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Operation::Send => "SEND",
+            Operation::SendWithImm => "SEND_WITH_IMM",
+            Operation::SendMixed => "SEND_MIXED",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "SEND" => Some(Self::Send),
+            "SEND_WITH_IMM" => Some(Self::SendWithImm),
+            "SEND_MIXED" => Some(Self::SendMixed),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ConnectionType {
+    RcTcp = 0,
+    RcCm = 1,
+    /// endpoints exchanged in Init, the queue pair has no async-rdma agent and
+    /// only carries SEND without scatter-gather layouts
+    RcGrpc = 3,
+}
+impl ConnectionType {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            ConnectionType::RcTcp => "RC_TCP",
+            ConnectionType::RcCm => "RC_CM",
+            ConnectionType::RcGrpc => "RC_GRPC",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "RC_TCP" => Some(Self::RcTcp),
+            "RC_CM" => Some(Self::RcCm),
+            "RC_GRPC" => Some(Self::RcGrpc),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ImmMode {
+    ImmConstant = 0,
+    ImmSequence = 1,
+    ImmJobId = 2,
+}
+impl ImmMode {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            ImmMode::ImmConstant => "IMM_CONSTANT",
+            ImmMode::ImmSequence => "IMM_SEQUENCE",
+            ImmMode::ImmJobId => "IMM_JOB_ID",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "IMM_CONSTANT" => Some(Self::ImmConstant),
+            "IMM_SEQUENCE" => Some(Self::ImmSequence),
+            "IMM_JOB_ID" => Some(Self::ImmJobId),
+            _ => None,
+        }
+    }
 }
 /// Generated client implementations.
 pub mod connection_client {
@@ -123,6 +380,78 @@ pub mod connection_client {
                 .insert(GrpcMethod::new("connection_manager.Connection", "Init"));
             self.inner.unary(req, path, codec).await
         }
+        pub async fn get_capabilities(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CapabilitiesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CapabilitiesReply>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/connection_manager.Connection/GetCapabilities",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("connection_manager.Connection", "GetCapabilities"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn get_job(
+            &mut self,
+            request: impl tonic::IntoRequest<super::JobRequest>,
+        ) -> std::result::Result<tonic::Response<super::JobResult>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/connection_manager.Connection/GetJob",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("connection_manager.Connection", "GetJob"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// stops waiting for the messages of a job and forgets its results
+        pub async fn cancel_job(
+            &mut self,
+            request: impl tonic::IntoRequest<super::JobRequest>,
+        ) -> std::result::Result<tonic::Response<super::JobResult>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/connection_manager.Connection/CancelJob",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("connection_manager.Connection", "CancelJob"));
+            self.inner.unary(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -136,6 +465,22 @@ pub mod connection_server {
             &self,
             request: tonic::Request<super::ConnectRequest>,
         ) -> std::result::Result<tonic::Response<super::ConnectReply>, tonic::Status>;
+        async fn get_capabilities(
+            &self,
+            request: tonic::Request<super::CapabilitiesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CapabilitiesReply>,
+            tonic::Status,
+        >;
+        async fn get_job(
+            &self,
+            request: tonic::Request<super::JobRequest>,
+        ) -> std::result::Result<tonic::Response<super::JobResult>, tonic::Status>;
+        /// stops waiting for the messages of a job and forgets its results
+        async fn cancel_job(
+            &self,
+            request: tonic::Request<super::JobRequest>,
+        ) -> std::result::Result<tonic::Response<super::JobResult>, tonic::Status>;
     }
     #[derive(Debug)]
     pub struct ConnectionServer<T: Connection> {
@@ -261,6 +606,140 @@ pub mod connection_server {
                     };
                     Box::pin(fut)
                 }
+                "/connection_manager.Connection/GetCapabilities" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetCapabilitiesSvc<T: Connection>(pub Arc<T>);
+                    impl<
+                        T: Connection,
+                    > tonic::server::UnaryService<super::CapabilitiesRequest>
+                    for GetCapabilitiesSvc<T> {
+                        type Response = super::CapabilitiesReply;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CapabilitiesRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Connection>::get_capabilities(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetCapabilitiesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/connection_manager.Connection/GetJob" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetJobSvc<T: Connection>(pub Arc<T>);
+                    impl<T: Connection> tonic::server::UnaryService<super::JobRequest>
+                    for GetJobSvc<T> {
+                        type Response = super::JobResult;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::JobRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Connection>::get_job(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetJobSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/connection_manager.Connection/CancelJob" => {
+                    #[allow(non_camel_case_types)]
+                    struct CancelJobSvc<T: Connection>(pub Arc<T>);
+                    impl<T: Connection> tonic::server::UnaryService<super::JobRequest>
+                    for CancelJobSvc<T> {
+                        type Response = super::JobResult;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::JobRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Connection>::cancel_job(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = CancelJobSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         Ok(
@@ -1,15 +1,139 @@
-use async_rdma::{LocalMrReadAccess, Rdma, RdmaBuilder, MTU};
+use std::alloc::Layout;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use hdrhistogram::Histogram;
+use async_rdma::{ConnectionType as RdmaConnectionType, LocalMrReadAccess, Rdma, RdmaBuilder, MTU};
+use futures::stream::{self, StreamExt};
 use crate::server::connection_manager::connection_manager::{
     connection_server::{Connection, ConnectionServer},
-    ConnectReply, ConnectRequest,
+    ConnectReply, ConnectRequest, ImmMode, JobSpec, Operation, ConnectionType, QpEndpoint,
+    CapabilitiesRequest, CapabilitiesReply, Device, JobRequest, JobResult,
 };
+use crate::capabilities::capabilities;
+use crate::rdma::rdma::{DeviceConfig, QpConfig, QosConfig};
+use crate::histogram::histogram;
+use crate::metrics::metrics;
+use crate::telemetry::telemetry;
+use crate::protocol::protocol;
+use crate::jobs::jobs::CancelToken;
 use tonic::transport::Server as GrpcServer;
-use log::{error, info};
+use tracing::{error, info, info_span, instrument, Instrument};
 use portpicker;
 
+// how long the results of a finished job wait for the initiator to fetch them
+const RESULT_TTL: Duration = Duration::from_secs(600);
+
 pub struct Server{
     address: String,
     port: u16,
+    device: DeviceConfig,
+    latencies: Latencies,
+}
+
+// jobs are told apart by the initiator's address, ids are only unique per initiator
+type JobKey = (Option<IpAddr>, u32);
+
+// Latencies collects the receive wait times of the connections of a job
+// until the initiator fetches them
+#[derive(Clone, Default)]
+struct Latencies {
+    jobs: Arc<Mutex<HashMap<JobKey, JobLatency>>>,
+}
+
+struct JobLatency {
+    active_connections: u32,
+    histogram: Histogram<u64>,
+    received: u64,
+    receive_errors: u64,
+    integrity_errors: u64,
+    finished: Option<Instant>,
+    token: CancelToken,
+}
+
+impl Latencies {
+    // open adds a connection to a job and returns the token it stops on.
+    // Sweeps and matrices connect again for every size and cell, so the
+    // connections of a job add up until the initiator fetches the result.
+    // Results never fetched expire
+    fn open(&self, key: JobKey) -> CancelToken {
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.retain(|_, job| job.finished.is_none_or(|finished| finished.elapsed() < RESULT_TTL));
+        let job = jobs.entry(key).or_default();
+        job.finished = None;
+        job.active_connections += 1;
+        job.token.clone()
+    }
+    fn close(&self, key: JobKey, latencies: &Histogram<u64>, received: u64, receive_errors: u64, integrity_errors: u64) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.get_mut(&key) {
+            job.active_connections -= 1;
+            job.received += received;
+            job.receive_errors += receive_errors;
+            job.integrity_errors += integrity_errors;
+            if let Err(e) = job.histogram.add(latencies) {
+                error!("failed to add latencies of {}: {}", key.1, e);
+            }
+            if job.active_connections == 0 {
+                job.finished = Some(Instant::now());
+                // nobody fetches the results of a cancelled job
+                if job.token.is_cancelled() {
+                    jobs.remove(&key);
+                }
+            }
+        }
+    }
+    // cancel stops the connections of a job and returns what they received
+    // so far, the job is forgotten once they are closed
+    fn cancel(&self, key: JobKey) -> Option<JobResult> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get(&key)?;
+        job.token.cancel();
+        let result = job.result(key.1);
+        if job.active_connections == 0 {
+            jobs.remove(&key);
+        }
+        Some(result)
+    }
+    // result returns the latencies of a job and forgets them once all of its
+    // connections are finished
+    fn result(&self, key: JobKey) -> Option<JobResult> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get(&key)?;
+        let result = job.result(key.1);
+        if job.active_connections == 0 {
+            jobs.remove(&key);
+        }
+        Some(result)
+    }
+}
+
+impl JobLatency {
+    fn result(&self, id: u32) -> JobResult {
+        JobResult{
+            id,
+            active_connections: self.active_connections,
+            receive_wait_histogram: histogram::encode(&self.histogram).unwrap_or_default(),
+            received: self.received,
+            receive_errors: self.receive_errors,
+            integrity_errors: self.integrity_errors,
+        }
+    }
+}
+
+impl Default for JobLatency {
+    fn default() -> Self {
+        JobLatency{
+            active_connections: 0,
+            histogram: histogram::new(),
+            received: 0,
+            receive_errors: 0,
+            integrity_errors: 0,
+            finished: None,
+            token: CancelToken::default(),
+        }
+    }
 }
 
 #[tonic::async_trait]
@@ -18,28 +142,70 @@ impl Connection for Server {
         &self,
         request: tonic::Request<ConnectRequest>,
     ) -> Result<tonic::Response<ConnectReply>, tonic::Status> {
-        info!("init request from {}", request.get_ref().id);
-        let port = portpicker::pick_unused_port().unwrap();
-        let address = self.address.clone();
-        let messages = request.get_ref().messages;
-        let message_size = request.get_ref().message_size;
-        let mtu = request.get_ref().mtu;
-        info!("spawning listener at {}:{}", address, port);
-        tokio::spawn(async move{
-            listener(address, port, messages, message_size, mtu).await
-        });
-        let reply = ConnectReply{
-            port: port as u32,
-        };
-        Ok(tonic::Response::new(reply))
+        let _timer = metrics::grpc("/connection_manager.Connection/Init");
+        let span = info_span!("init", job_id = job_id(request.get_ref()));
+        telemetry::join(&span, request.metadata());
+        let peer = request.remote_addr().map(|addr| addr.ip());
+        self.init_job(peer, request.into_inner()).instrument(span).await
+    }
+    async fn get_capabilities(
+        &self,
+        _request: tonic::Request<CapabilitiesRequest>,
+    ) -> Result<tonic::Response<CapabilitiesReply>, tonic::Status> {
+        let _timer = metrics::grpc("/connection_manager.Connection/GetCapabilities");
+        Ok(tonic::Response::new(server_capabilities()))
+    }
+    async fn get_job(
+        &self,
+        request: tonic::Request<JobRequest>,
+    ) -> Result<tonic::Response<JobResult>, tonic::Status> {
+        let _timer = metrics::grpc("/connection_manager.Connection/GetJob");
+        let id = request.get_ref().id;
+        let peer = request.remote_addr().map(|addr| addr.ip());
+        match self.latencies.result((peer, id)) {
+            Some(result) => Ok(tonic::Response::new(result)),
+            None => Err(tonic::Status::not_found(format!("job {} not found", id))),
+        }
+    }
+    async fn cancel_job(
+        &self,
+        request: tonic::Request<JobRequest>,
+    ) -> Result<tonic::Response<JobResult>, tonic::Status> {
+        let _timer = metrics::grpc("/connection_manager.Connection/CancelJob");
+        let id = request.get_ref().id;
+        let peer = request.remote_addr().map(|addr| addr.ip());
+        match self.latencies.cancel((peer, id)) {
+            Some(result) => {
+                info!("job {} cancelled", id);
+                Ok(tonic::Response::new(result))
+            },
+            None => Err(tonic::Status::not_found(format!("job {} not found", id))),
+        }
+    }
+}
+
+fn server_capabilities() -> CapabilitiesReply {
+    CapabilitiesReply{
+        version: capabilities::VERSION.to_string(),
+        operations: vec![
+            Operation::Send.into(),
+            Operation::SendWithImm.into(),
+            Operation::SendMixed.into(),
+        ],
+        transports: capabilities::TRANSPORTS.iter().map(|t| t.to_string()).collect(),
+        mtus: capabilities::MTUS.to_vec(),
+        max_message_size: capabilities::MAX_MESSAGE_SIZE,
+        devices: capabilities::devices().into_iter().map(Device::from).collect(),
     }
 }
 
 impl Server {
-    pub fn new(address: String, port: u16) -> Server {
+    pub fn new(address: String, port: u16, device: DeviceConfig) -> Server {
         Server{
             address,
             port,
+            device,
+            latencies: Latencies::default(),
         }
     }
     pub async fn run(self) -> anyhow::Result<()> {
@@ -51,6 +217,91 @@ impl Server {
             (Err(e),) => Err(e),
         }
     }
+    // init_job sets up the receiving side of a job, it runs in the span of
+    // the initiator's trace
+    async fn init_job(&self, peer: Option<IpAddr>, request: ConnectRequest) -> Result<tonic::Response<ConnectReply>, tonic::Status> {
+        let address = self.address.clone();
+        let endpoint = request.endpoint.clone();
+        let spec = job_spec(request);
+        info!("init request from {}", spec.id);
+        if spec.version > protocol::JOB_SPEC_VERSION {
+            info!("job spec version {} is newer than {}, ignoring unknown fields", spec.version, protocol::JOB_SPEC_VERSION);
+        }
+        let device = DeviceConfig::new(spec.device.clone(), spec.ib_port, spec.gid_index)
+            .map_err(|e| tonic::Status::invalid_argument(e.to_string()))?;
+        let device = self.device.merge(device);
+        let qp = QpConfig{
+            max_send_wr: spec.max_send_wr,
+            max_recv_wr: spec.max_recv_wr,
+            max_sge: spec.max_sge,
+            cq_size: spec.cq_size,
+            max_inline_data: spec.max_inline_data,
+            retry_count: spec.retry_count,
+            rnr_retry: spec.rnr_retry,
+            timeout: spec.timeout,
+            min_rnr_timer: spec.min_rnr_timer,
+        };
+        qp.validate().map_err(|e| tonic::Status::invalid_argument(e.to_string()))?;
+        let qos = QosConfig{
+            traffic_class: spec.traffic_class,
+            service_level: spec.service_level,
+            flow_label: spec.flow_label,
+        };
+        qos.validate().map_err(|e| tonic::Status::invalid_argument(e.to_string()))?;
+        qos.supported().map_err(|e| tonic::Status::unimplemented(e.to_string()))?;
+        let builder = builder(&spec, &device, &qp);
+        if spec.connection_type == ConnectionType::RcGrpc as i32 {
+            if spec.op != Operation::Send as i32 {
+                return Err(tonic::Status::invalid_argument("RC_GRPC connections only support SEND"));
+            }
+            let remote = endpoint.ok_or_else(|| tonic::Status::invalid_argument("missing qp endpoint"))?;
+            let remote = remote.try_into().map_err(|e: anyhow::Error| tonic::Status::invalid_argument(e.to_string()))?;
+            // creating and connecting the queue pair are blocking verbs calls
+            let (rdma, local) = tokio::task::spawn_blocking(move || {
+                let mut rdma = builder.build()?;
+                let local = QpEndpoint::try_from(rdma.get_qp_endpoint())?;
+                rdma.qp_handshake(remote)?;
+                Ok::<_, anyhow::Error>((rdma, local))
+            }).await.
+                map_err(|e| tonic::Status::internal(e.to_string()))?.
+                map_err(|e| tonic::Status::internal(e.to_string()))?;
+            info!("qp handshake done for {}", spec.id);
+            let key = (peer, spec.id);
+            let latencies = self.latencies.clone();
+            let token = latencies.open(key);
+            metrics::add(metrics::ACTIVE_LISTENERS, &[], 1.0);
+            tokio::spawn(async move{
+                let res = receiver(rdma, spec, key, token, latencies).await;
+                metrics::add(metrics::ACTIVE_LISTENERS, &[], -1.0);
+                res
+            }.in_current_span());
+            let reply = ConnectReply{
+                port: 0,
+                endpoint: Some(local),
+            };
+            return Ok(tonic::Response::new(reply));
+        }
+        let port = portpicker::pick_unused_port().unwrap();
+        info!("spawning listener at {}:{}", address, port);
+        let key = (peer, spec.id);
+        let latencies = self.latencies.clone();
+        let token = latencies.open(key);
+        metrics::add(metrics::ACTIVE_LISTENERS, &[], 1.0);
+        tokio::spawn(async move{
+            let id = spec.id;
+            if let Err(e) = listener(address, port, spec, key, token, builder, latencies.clone()).await {
+                error!("listener error for {}: {}", id, e);
+                metrics::inc(metrics::ERRORS, &[("kind", "listen")]);
+                latencies.close(key, &histogram::new(), 0, 0, 0);
+            }
+            metrics::add(metrics::ACTIVE_LISTENERS, &[], -1.0);
+        }.in_current_span());
+        let reply = ConnectReply{
+            port: port as u32,
+            endpoint: None,
+        };
+        Ok(tonic::Response::new(reply))
+    }
     async fn grpc_server(self) -> anyhow::Result<()> {
         let address = format!("{}:{}", self.address, self.port);
         info!("starting grpc server at {}", address);
@@ -65,36 +316,192 @@ impl Server {
 
 }
 
-async fn listener(address: String, port: u16, messages: u32, message_size: u32, mtu: u32) -> anyhow::Result<()> {
-    let address = format!("{}:{}", address, port);
-    let mtu = match mtu {
+// job_id is the id of the job of an Init request from any initiator version
+#[allow(deprecated)]
+fn job_id(request: &ConnectRequest) -> u32 {
+    request.spec.as_ref().map_or(request.id, |spec| spec.id)
+}
+
+// job_spec returns the spec sent by the initiator or, for initiators
+// predating JobSpec, a version 0 spec built from the flat fields
+#[allow(deprecated)]
+fn job_spec(request: ConnectRequest) -> JobSpec {
+    match request.spec {
+        Some(spec) => spec,
+        None => JobSpec{
+            version: 0,
+            id: request.id,
+            op: request.op,
+            messages: request.messages,
+            message_size: request.message_size,
+            mtu: request.mtu,
+            imm_mode: request.imm_mode,
+            imm_data: request.imm_data,
+            ..Default::default()
+        },
+    }
+}
+
+fn builder(spec: &JobSpec, device: &DeviceConfig, qp: &QpConfig) -> RdmaBuilder {
+    let mtu = match spec.mtu {
         512 => MTU::MTU512,
         1024 => MTU::MTU1024,
         2048 => MTU::MTU2048,
         4096 => MTU::MTU4096,
         _ => MTU::MTU1024,
     };
+    qp.apply(device.apply(RdmaBuilder::default())).
+        set_max_message_length(spec.message_size as usize).
+        set_mtu(mtu)
+}
+
+#[instrument(skip_all, fields(job_id = spec.id))]
+#[allow(clippy::too_many_arguments)]
+async fn listener(address: String, port: u16, spec: JobSpec, key: JobKey, token: CancelToken, builder: RdmaBuilder, latencies: Latencies) -> anyhow::Result<()> {
+    let address = format!("{}:{}", address, port);
     info!("listening for rdma at {}", address);
-    let rdma = RdmaBuilder::default().
-        set_max_message_length(message_size as usize).
-        set_mtu(mtu).
-        listen(address.clone()).await?;
-    for _ in 0..messages{
-        let res = tokio::select! {
-            ret = receive(&rdma) => {
-                ret
+    let listen = async {
+        match ConnectionType::try_from(spec.connection_type).unwrap_or(ConnectionType::RcTcp) {
+            ConnectionType::RcCm => builder.set_conn_type(RdmaConnectionType::RCCM).listen(address.clone()).await,
+            _ => builder.listen(address.clone()).await,
+        }
+    };
+    let rdma = tokio::select! {
+        rdma = listen => rdma?,
+        _ = token.cancelled() => {
+            info!("job {} cancelled before the initiator connected", spec.id);
+            latencies.close(key, &histogram::new(), 0, 0, 0);
+            return Ok(());
+        },
+    };
+    receiver(rdma, spec, key, token, latencies).await
+}
+
+// receiver receives the messages of a job and records the receive wait from
+// posting each receive to its completion, which includes the time until the
+// initiator sent the message. Receives of warmup messages are not recorded
+// The receive buffers are allocated by async-rdma, the receive calls take no
+// caller regions, so they can't come from an MrPool
+#[instrument(skip_all, fields(job_id = spec.id))]
+async fn receiver(rdma: Rdma, spec: JobSpec, key: JobKey, token: CancelToken, latencies: Latencies) -> anyhow::Result<()> {
+    let op = Operation::try_from(spec.op).unwrap_or(Operation::Send);
+    let mut immediates = Vec::new();
+    let mut plain = 0;
+    let mut errors = 0;
+    let mut received = 0;
+    // up to tx_depth receives are awaited at once, completions of
+    // concurrent sends may arrive in any order
+    let rdma = &rdma;
+    let mut histogram = histogram::new();
+    let timeout = if spec.receive_timeout_ms > 0 { Duration::from_millis(spec.receive_timeout_ms as u64) } else { protocol::DEFAULT_RECEIVE_TIMEOUT };
+    let mut deadline = tokio::time::Instant::now() + until(spec.start_at_unix_ns) + timeout;
+    // RC_GRPC queue pairs have no agent to hand out receive buffers
+    let raw = spec.connection_type == ConnectionType::RcGrpc as i32;
+    let message_size = spec.message_size;
+    let mut results = stream::iter(0..spec.messages).map(|_| async move {
+        let start = Instant::now();
+        let res = match op {
+            Operation::Send if raw => receive_raw(rdma, message_size).await.map(|_| None),
+            Operation::Send => receive(rdma).await.map(|_| None),
+            Operation::SendWithImm => match receive_with_imm(rdma).await {
+                Ok(None) => Err(anyhow::anyhow!("missing immediate")),
+                res => res,
             },
-            ret = receive_with_imm(&rdma) => {
-                ret
+            Operation::SendMixed => receive_with_imm(rdma).await,
+        };
+        (res, start.elapsed())
+    }).buffered(spec.tx_depth.max(1) as usize);
+    loop {
+        let next = tokio::select! {
+            next = tokio::time::timeout_at(deadline, results.next()) => next,
+            _ = token.cancelled() => {
+                info!("job {} cancelled after {} of {} messages", spec.id, received + errors, spec.messages);
+                break;
             },
         };
-        if let Err(e) = res {
-            error!("receive error: {}", e);
+        let (res, latency) = match next {
+            Ok(Some(result)) => result,
+            Ok(None) => break,
+            Err(_) => {
+                error!("no message for {} ms, giving up after {} of {} messages", timeout.as_millis(), received + errors, spec.messages);
+                metrics::inc(metrics::ERRORS, &[("kind", "receive_timeout")]);
+                break;
+            },
+        };
+        deadline = tokio::time::Instant::now() + timeout;
+        if res.is_ok() {
+            metrics::received(spec.message_size as u64);
+            if received + errors >= spec.warmup_messages as u64 {
+                histogram::record(&mut histogram, latency);
+            }
+            received += 1;
         }
+        match res {
+            Ok(Some(imm)) => immediates.push(imm),
+            Ok(None) => plain += 1,
+            Err(e) => {
+                error!("receive error: {}", e);
+                metrics::inc(metrics::ERRORS, &[("kind", "receive")]);
+                errors += 1;
+            },
+        }
+    }
+    let mut integrity_errors = 0;
+    if spec.skip_integrity_check {
+        info!("received {} plain messages and {} messages with immediate for {}", plain, immediates.len(), spec.id);
+    } else if op == Operation::SendMixed {
+        let expected = spec.messages / 2;
+        if immediates.len() as u32 != expected {
+            error!("expected {} messages with immediate for {}, got {}", expected, spec.id, immediates.len());
+            integrity_errors += expected.abs_diff(immediates.len() as u32) as u64;
+        }
+        info!("received {} plain messages and {} messages with immediate for {}", plain, immediates.len(), spec.id);
     }
+    if op != Operation::Send && !spec.skip_integrity_check {
+        let mismatches = verify_immediates(&spec, &immediates);
+        metrics::add(metrics::ERRORS, &[("kind", "immediate_mismatch")], mismatches as f64);
+        integrity_errors += mismatches as u64;
+        info!("received {} immediates for {}, {} mismatches", immediates.len(), spec.id, mismatches);
+    }
+    latencies.close(key, &histogram, received, errors, integrity_errors);
     Ok(())
 }
 
+// until returns the time left until a wall clock time in nanoseconds since
+// the unix epoch, 0 or a time in the past is now
+fn until(unix_ns: u64) -> Duration {
+    (UNIX_EPOCH + Duration::from_nanos(unix_ns)).duration_since(SystemTime::now()).unwrap_or_default()
+}
+
+// verify_immediates compares the received immediates against the ones the
+// initiator generates for the job and returns the number of received ones
+// it didn't send. Concurrent sends complete in any order, so they are
+// compared as a multiset
+fn verify_immediates(spec: &JobSpec, immediates: &[u32]) -> usize {
+    let imm_mode = ImmMode::try_from(spec.imm_mode).unwrap_or(ImmMode::ImmConstant);
+    let sent = match Operation::try_from(spec.op) {
+        Ok(Operation::SendMixed) => spec.messages / 2,
+        _ => spec.messages,
+    };
+    let mut expected: HashMap<u32, u32> = HashMap::new();
+    for seq in 0..sent {
+        *expected.entry(protocol::immediate(imm_mode, spec.imm_data, spec.id, seq)).or_default() += 1;
+    }
+    let mut mismatches = 0;
+    for imm in immediates {
+        match expected.get_mut(imm) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => {
+                if mismatches == 0 {
+                    error!("unexpected immediate {}", imm);
+                }
+                mismatches += 1;
+            },
+        }
+    }
+    mismatches
+}
+
 pub async fn receive(rdma: &Rdma) -> anyhow::Result<()> {
     let lmr = rdma.receive().await?;
     let _data = *lmr.as_slice();
@@ -102,8 +509,118 @@ pub async fn receive(rdma: &Rdma) -> anyhow::Result<()> {
     
 }
 
-pub async fn receive_with_imm(rdma: &Rdma) -> anyhow::Result<()> {
-    let (lmr, _imm) = rdma.receive_with_imm().await?;
+// receive_raw posts a receive of message_size bytes on a queue pair
+// without an agent
+pub async fn receive_raw(rdma: &Rdma, message_size: u32) -> anyhow::Result<()> {
+    let layout = Layout::from_size_align(message_size as usize, 1)?;
+    let lmr = rdma.receive_raw(layout).await?;
     let _data = *lmr.as_slice();
     Ok(())
-}
\ No newline at end of file
+}
+
+pub async fn receive_with_imm(rdma: &Rdma) -> anyhow::Result<Option<u32>> {
+    let (lmr, imm) = rdma.receive_with_imm().await?;
+    let _data = *lmr.as_slice();
+    Ok(imm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(op: Operation, messages: u32) -> JobSpec {
+        JobSpec{
+            id: 9,
+            op: op.into(),
+            messages,
+            imm_mode: ImmMode::ImmSequence.into(),
+            imm_data: 100,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn immediates_in_any_order_match() {
+        assert_eq!(verify_immediates(&spec(Operation::SendWithImm, 4), &[103, 101, 100, 102]), 0);
+    }
+
+    #[test]
+    fn duplicate_immediates_are_mismatches() {
+        assert_eq!(verify_immediates(&spec(Operation::SendWithImm, 4), &[100, 101, 101, 103]), 1);
+        assert_eq!(verify_immediates(&spec(Operation::SendWithImm, 2), &[100, 101, 100, 101]), 2);
+    }
+
+    #[test]
+    fn missing_immediates_are_not_mismatches() {
+        // missing messages are counted by what was received
+        assert_eq!(verify_immediates(&spec(Operation::SendWithImm, 4), &[100, 102]), 0);
+    }
+
+    #[test]
+    fn unexpected_immediates_are_mismatches() {
+        assert_eq!(verify_immediates(&spec(Operation::SendWithImm, 2), &[100, 7]), 1);
+    }
+
+    #[test]
+    fn mixed_jobs_expect_half_the_messages_with_immediate() {
+        assert_eq!(verify_immediates(&spec(Operation::SendMixed, 4), &[100, 101]), 0);
+        assert_eq!(verify_immediates(&spec(Operation::SendMixed, 4), &[100, 101, 102]), 1);
+    }
+
+    #[test]
+    fn constant_immediates_are_counted() {
+        let spec = JobSpec{ imm_mode: ImmMode::ImmConstant.into(), ..spec(Operation::SendWithImm, 3) };
+        assert_eq!(verify_immediates(&spec, &[100, 100, 100]), 0);
+        assert_eq!(verify_immediates(&spec, &[100, 100, 100, 100]), 1);
+    }
+
+    #[test]
+    fn connections_of_a_job_add_up() {
+        let latencies = Latencies::default();
+        let key = (None, 1);
+        latencies.open(key);
+        latencies.close(key, &histogram::new(), 10, 1, 0);
+        latencies.open(key);
+        latencies.close(key, &histogram::new(), 5, 0, 2);
+        let result = latencies.result(key).unwrap();
+        assert_eq!((result.received, result.receive_errors, result.integrity_errors), (15, 1, 2));
+        assert_eq!(result.active_connections, 0);
+    }
+
+    #[test]
+    fn fetched_results_are_forgotten() {
+        let latencies = Latencies::default();
+        let key = (None, 1);
+        latencies.open(key);
+        latencies.close(key, &histogram::new(), 10, 0, 0);
+        assert!(latencies.result(key).is_some());
+        assert!(latencies.result(key).is_none());
+        latencies.open(key);
+        latencies.close(key, &histogram::new(), 3, 0, 0);
+        assert_eq!(latencies.result(key).unwrap().received, 3);
+    }
+
+    #[test]
+    fn jobs_of_different_initiators_are_apart() {
+        let latencies = Latencies::default();
+        let first = (Some(IpAddr::from([10, 0, 0, 1])), 1);
+        let second = (Some(IpAddr::from([10, 0, 0, 2])), 1);
+        latencies.open(first);
+        latencies.open(second);
+        latencies.close(first, &histogram::new(), 4, 0, 0);
+        latencies.close(second, &histogram::new(), 6, 0, 0);
+        assert_eq!(latencies.result(first).unwrap().received, 4);
+        assert_eq!(latencies.result(second).unwrap().received, 6);
+    }
+
+    #[test]
+    fn cancelled_jobs_are_forgotten_once_closed() {
+        let latencies = Latencies::default();
+        let key = (None, 1);
+        let token = latencies.open(key);
+        assert_eq!(latencies.cancel(key).unwrap().active_connections, 1);
+        assert!(token.is_cancelled());
+        latencies.close(key, &histogram::new(), 2, 0, 0);
+        assert!(latencies.result(key).is_none());
+    }
+}
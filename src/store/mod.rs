@@ -0,0 +1 @@
+pub mod store;
\ No newline at end of file
@@ -0,0 +1,197 @@
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use prost::Message;
+use rusqlite::{params, Connection, ToSql};
+use tracing::error;
+use crate::initiator::listener::listener::{JobStatus, Operation, QueryResultsRequest, SendRequest, StoredResult};
+use crate::metrics::metrics;
+
+// results returned by a query without a limit
+const DEFAULT_LIMIT: u32 = 1000;
+
+// a job has one row in results, the sizes it measured and its tags have
+// rows of their own to filter by. Spec and status are the encoded protos,
+// the hosts are the addresses of initiator and server without their ports
+const SCHEMA: &str = "
+PRAGMA journal_mode = WAL;
+CREATE TABLE IF NOT EXISTS results (
+    id INTEGER PRIMARY KEY,
+    job_id INTEGER NOT NULL,
+    finished_unix_ms INTEGER NOT NULL,
+    initiator TEXT NOT NULL,
+    initiator_host TEXT NOT NULL,
+    server TEXT NOT NULL,
+    server_host TEXT NOT NULL,
+    op TEXT NOT NULL,
+    state TEXT NOT NULL,
+    spec BLOB NOT NULL,
+    status BLOB NOT NULL
+);
+CREATE INDEX IF NOT EXISTS results_finished ON results (finished_unix_ms);
+CREATE INDEX IF NOT EXISTS results_initiator ON results (initiator_host);
+CREATE INDEX IF NOT EXISTS results_server ON results (server_host);
+CREATE TABLE IF NOT EXISTS result_sizes (
+    result_id INTEGER NOT NULL REFERENCES results (id),
+    message_size INTEGER NOT NULL,
+    PRIMARY KEY (result_id, message_size)
+);
+CREATE INDEX IF NOT EXISTS result_sizes_size ON result_sizes (message_size);
+CREATE TABLE IF NOT EXISTS result_tags (
+    result_id INTEGER NOT NULL REFERENCES results (id),
+    tag TEXT NOT NULL,
+    PRIMARY KEY (result_id, tag)
+);
+CREATE INDEX IF NOT EXISTS result_tags_tag ON result_tags (tag);
+";
+
+// Store keeps the spec and results of finished jobs in a sqlite database.
+// Jobs are written by a thread of their own and queries run on the
+// blocking pool, neither holds up the runtime
+#[derive(Debug, Clone)]
+pub struct Store {
+    records: Sender<Record>,
+    reader: Arc<Mutex<Connection>>,
+}
+
+// Record is a finished job waiting to be written
+#[derive(Debug)]
+struct Record {
+    initiator: String,
+    finished_unix_ms: i64,
+    spec: SendRequest,
+    status: JobStatus,
+}
+
+impl Store {
+    pub fn open(path: &Path) -> anyhow::Result<Store> {
+        let writer = Connection::open(path)?;
+        writer.execute_batch(SCHEMA)?;
+        let reader = Connection::open(path)?;
+        let (records, receiver) = mpsc::channel();
+        std::thread::Builder::new().name("results-writer".to_string()).spawn(move || write(writer, receiver))?;
+        Ok(Store{
+            records,
+            reader: Arc::new(Mutex::new(reader)),
+        })
+    }
+    // record queues a finished job of initiator to be stored
+    pub fn record(&self, initiator: &str, spec: &SendRequest, status: &JobStatus) -> anyhow::Result<()> {
+        let record = Record{
+            initiator: initiator.to_string(),
+            finished_unix_ms: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as i64,
+            spec: spec.clone(),
+            status: status.clone(),
+        };
+        self.records.send(record).map_err(|_| anyhow::anyhow!("the results writer stopped"))
+    }
+    // query returns the most recent results matching query, newest first
+    pub async fn query(&self, query: QueryResultsRequest) -> anyhow::Result<Vec<StoredResult>> {
+        let reader = self.reader.clone();
+        tokio::task::spawn_blocking(move || select(&reader.lock().unwrap(), &query)).await?
+    }
+}
+
+// write stores the records until every Store is dropped
+fn write(mut conn: Connection, records: Receiver<Record>) {
+    for record in records {
+        if let Err(e) = insert(&mut conn, &record) {
+            error!("failed to store the results of job {}: {}", record.spec.id, e);
+            metrics::inc(metrics::ERRORS, &[("kind", "store")]);
+        }
+    }
+}
+
+fn insert(conn: &mut Connection, record: &Record) -> anyhow::Result<()> {
+    let Record{ initiator, finished_unix_ms, spec, status } = record;
+    let op = Operation::try_from(spec.op).map(|op| op.as_str_name()).unwrap_or("UNKNOWN");
+    let state = status.state().as_str_name();
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO results (job_id, finished_unix_ms, initiator, initiator_host, server, server_host, op, state, spec, status) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![spec.id, finished_unix_ms, initiator, host(initiator), spec.address, host(&spec.address), op, state, spec.encode_to_vec(), status.encode_to_vec()],
+    )?;
+    let id = tx.last_insert_rowid();
+    for size in sizes(spec, status) {
+        tx.execute("INSERT OR IGNORE INTO result_sizes (result_id, message_size) VALUES (?1, ?2)", params![id, size])?;
+    }
+    for tag in &spec.tags {
+        tx.execute("INSERT OR IGNORE INTO result_tags (result_id, tag) VALUES (?1, ?2)", params![id, tag])?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+// select runs query, initiator and server match a whole address or a host
+fn select(conn: &Connection, query: &QueryResultsRequest) -> anyhow::Result<Vec<StoredResult>> {
+    let mut sql = String::from("SELECT id, finished_unix_ms, initiator, spec, status FROM results WHERE finished_unix_ms >= ?");
+    let mut args: Vec<Box<dyn ToSql>> = vec![Box::new(query.from_unix_ms as i64)];
+    if query.to_unix_ms > 0 {
+        sql.push_str(" AND finished_unix_ms <= ?");
+        args.push(Box::new(query.to_unix_ms as i64));
+    }
+    for (column, address) in [("initiator", &query.initiator), ("server", &query.server)] {
+        if !address.is_empty() {
+            sql.push_str(&format!(" AND ({0} = ? OR {0}_host = ?)", column));
+            args.push(Box::new(address.clone()));
+            args.push(Box::new(address.clone()));
+        }
+    }
+    if query.op.is_some() {
+        sql.push_str(" AND op = ?");
+        args.push(Box::new(query.op().as_str_name()));
+    }
+    if query.message_size > 0 {
+        sql.push_str(" AND id IN (SELECT result_id FROM result_sizes WHERE message_size = ?)");
+        args.push(Box::new(query.message_size));
+    }
+    for tag in &query.tags {
+        sql.push_str(" AND id IN (SELECT result_id FROM result_tags WHERE tag = ?)");
+        args.push(Box::new(tag.clone()));
+    }
+    sql.push_str(" ORDER BY finished_unix_ms DESC, id DESC LIMIT ?");
+    args.push(Box::new(if query.limit > 0 { query.limit } else { DEFAULT_LIMIT }));
+    let mut statement = conn.prepare(&sql)?;
+    let rows = statement.query_map(rusqlite::params_from_iter(args.iter()), |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, String>(2)?, row.get::<_, Vec<u8>>(3)?, row.get::<_, Vec<u8>>(4)?))
+    })?;
+    let mut results = Vec::new();
+    for row in rows {
+        let (id, finished, initiator, spec, status) = row?;
+        results.push(StoredResult{
+            id: id as u64,
+            finished_unix_ms: finished as u64,
+            initiator,
+            spec: Some(SendRequest::decode(spec.as_slice())?),
+            status: Some(JobStatus::decode(status.as_slice())?),
+        });
+    }
+    Ok(results)
+}
+
+// host is address without its port, addresses without a port are hosts
+fn host(address: &str) -> &str {
+    if address.parse::<IpAddr>().is_ok() {
+        return address;
+    }
+    match address.rsplit_once(':') {
+        Some((host, port)) if port.parse::<u16>().is_ok() => host.trim_start_matches('[').trim_end_matches(']'),
+        _ => address,
+    }
+}
+
+// sizes are the message sizes a job asked for and the ones it measured
+fn sizes(spec: &SendRequest, status: &JobStatus) -> Vec<u32> {
+    let mut sizes = match &spec.matrix {
+        Some(matrix) if !matrix.message_sizes.is_empty() => matrix.message_sizes.clone(),
+        _ => vec![spec.message_size],
+    };
+    sizes.extend(&spec.sweep_sizes);
+    sizes.extend(status.measurements.iter().map(|m| m.message_size));
+    sizes.extend(status.cells.iter().map(|c| c.message_size));
+    sizes.sort_unstable();
+    sizes.dedup();
+    sizes
+}
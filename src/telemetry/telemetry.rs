@@ -0,0 +1,79 @@
+use opentelemetry::{global, KeyValue};
+use opentelemetry::propagation::{Extractor, Injector};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::{runtime, trace, Resource};
+use tonic::metadata::{KeyRef, MetadataKey, MetadataMap, MetadataValue};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+const SERVICE_NAME: &str = "rocky-rs";
+
+// init installs the log output filtered by RUST_LOG and, with an endpoint,
+// exports the spans to an OTLP collector over gRPC
+pub fn init(otlp_endpoint: Option<String>) -> anyhow::Result<()> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let otel = match otlp_endpoint {
+        Some(endpoint) => {
+            let tracer = opentelemetry_otlp::new_pipeline()
+                .tracing()
+                .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(endpoint))
+                .with_trace_config(trace::config().with_resource(Resource::new(vec![
+                    KeyValue::new("service.name", SERVICE_NAME),
+                ])))
+                .install_batch(runtime::Tokio)?;
+            Some(tracing_opentelemetry::layer().with_tracer(tracer))
+        },
+        None => None,
+    };
+    tracing_subscriber::registry()
+        .with(EnvFilter::from_default_env())
+        .with(tracing_subscriber::fmt::layer())
+        .with(otel)
+        .try_init()?;
+    Ok(())
+}
+
+// shutdown flushes the spans which are not exported yet
+pub fn shutdown() {
+    global::shutdown_tracer_provider();
+}
+
+struct MetadataInjector<'a>(&'a mut MetadataMap);
+
+impl Injector for MetadataInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(key), Ok(value)) = (MetadataKey::from_bytes(key.as_bytes()), MetadataValue::try_from(value.as_str())) {
+            self.0.insert(key, value);
+        }
+    }
+}
+
+struct MetadataExtractor<'a>(&'a MetadataMap);
+
+impl Extractor for MetadataExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| match key {
+            KeyRef::Ascii(key) => key.as_str(),
+            KeyRef::Binary(key) => key.as_str(),
+        }).collect()
+    }
+}
+
+// inject adds the trace context of the current span to outgoing gRPC metadata
+pub fn inject(metadata: &mut MetadataMap) {
+    let context = tracing::Span::current().context();
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&context, &mut MetadataInjector(metadata)));
+}
+
+// join makes span a child of the trace context in incoming gRPC metadata,
+// spans of requests without one start a new trace
+pub fn join(span: &tracing::Span, metadata: &MetadataMap) {
+    let context = global::get_text_map_propagator(|propagator| propagator.extract(&MetadataExtractor(metadata)));
+    span.set_parent(context);
+}